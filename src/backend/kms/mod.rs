@@ -4,12 +4,15 @@
 use crate::backend::render::element::AsGlowRenderer;
 use crate::{
     backend::render::{workspace_elements, CLEAR_COLOR},
-    config::OutputConfig,
+    config::{DmabufConfig, ModifierPolicy, OutputConfig},
     shell::Shell,
     state::{BackendData, ClientState, Common, Data, Fps, SurfaceDmabufFeedback},
     utils::prelude::*,
     wayland::{
-        handlers::screencopy::{render_session, UserdataExt},
+        handlers::{
+            dmabuf::{describe_dmabuf, fits_texture_size, supported_modifiers},
+            screencopy::{render_session, UserdataExt},
+        },
         protocols::screencopy::{BufferParams, Session as ScreencopySession},
     },
 };
@@ -19,10 +22,10 @@ use cosmic_protocols::screencopy::v1::server::zcosmic_screencopy_session_v1::Fai
 use smithay::{
     backend::{
         allocator::{
-            dmabuf::{AnyError, Dmabuf, DmabufAllocator},
+            dmabuf::{AnyError, Dmabuf, DmabufAllocator, WeakDmabuf},
             gbm::{GbmAllocator, GbmBufferFlags, GbmDevice},
             vulkan::{ImageUsageFlags, VulkanAllocator},
-            Allocator, Format, Fourcc,
+            Allocator, Format, Fourcc, Modifier,
         },
         drm::{
             compositor::{BlitFrameResultError, DrmCompositor, FrameError},
@@ -67,6 +70,7 @@ use smithay::{
     },
     utils::{DeviceFd, Size, Transform},
     wayland::{
+        compositor::with_states,
         dmabuf::{get_dmabuf, DmabufFeedbackBuilder, DmabufGlobal},
         relative_pointer::RelativePointerManagerState,
         seat::WaylandFocus,
@@ -74,7 +78,7 @@ use smithay::{
     },
     xwayland::XWaylandClientData,
 };
-use tracing::{error, info, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 
 use std::{
     cell::RefCell,
@@ -83,6 +87,7 @@ use std::{
     fmt,
     os::unix::io::FromRawFd,
     path::PathBuf,
+    sync::Mutex,
     time::Duration,
 };
 
@@ -90,7 +95,7 @@ mod drm_helpers;
 mod socket;
 use socket::*;
 
-use super::render::{init_shaders, CursorMode, GlMultiRenderer};
+use super::render::{init_shaders, max_texture_size, CursorMode, GlMultiRenderer};
 // for now we assume we need at least 3ms
 const MIN_RENDER_TIME: Duration = Duration::from_millis(3);
 
@@ -100,6 +105,11 @@ pub struct KmsState {
     pub input_devices: HashMap<String, input::Device>,
     pub api: GpuManager<GbmGlesBackend<GlowRenderer>>,
     pub primary: DrmNode,
+    /// Formats of client buffers already logged for using a modifier, which isn't preferred,
+    /// since the last GPU was added or removed
+    warned_formats: HashSet<Format>,
+    /// Client buffers imported on another GPU than the one of their global, and that GPU
+    fallback_imports: Vec<(WeakDmabuf, DrmNode)>,
     session: LibSeatSession,
     _tokens: Vec<RegistrationToken>,
 }
@@ -132,6 +142,17 @@ impl fmt::Debug for Device {
     }
 }
 
+/// Whether buffers of a given format can be shown by the display hardware without compositing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanoutSupport {
+    /// The primary plane supports the format, so a fullscreen buffer can be scanned out directly
+    Primary,
+    /// Only overlay planes support the format
+    Overlay,
+    /// The format has to be composited
+    Unsupported,
+}
+
 #[derive(Debug)]
 pub struct Surface {
     surface: Option<GbmDrmCompositor>,
@@ -369,6 +390,8 @@ pub fn init_backend(
             udev_event_source,
         ],
         primary,
+        warned_formats: HashSet::new(),
+        fallback_imports: Vec::new(),
         session,
         devices: HashMap::new(),
         input_devices: HashMap::new(),
@@ -572,7 +595,9 @@ impl State {
             )
             .with_context(|| format!("Failed to add drm device to event loop: {}", dev))?;
 
-        let socket = match self.create_socket(dh, render_node, formats.clone().into_iter()) {
+        let advertised_formats =
+            advertised_formats(&formats, &self.common.config.static_conf.dmabuf);
+        let socket = match self.create_socket(dh, render_node, advertised_formats.into_iter()) {
             Ok(socket) => Some(socket),
             Err(err) => {
                 warn!(
@@ -641,6 +666,8 @@ impl State {
                 };
             }
             backend.devices.insert(drm_node, device);
+            // preferred modifiers depend on the available GPUs
+            backend.warned_formats.clear();
         }
 
         self.common
@@ -798,6 +825,7 @@ impl State {
                     .destroy_global::<State>(dh, socket.dmabuf_global);
                 dh.remove_global::<State>(socket.drm_global);
             }
+            backend.warned_formats.clear();
         }
         self.common
             .output_configuration_state
@@ -946,7 +974,20 @@ impl Device {
     }
 }
 
+/// GPU the buffer attached to a surface was imported on, instead of the one of the client
+struct FallbackNode(Mutex<Option<DrmNode>>);
+
 pub fn source_node_for_surface(w: &WlSurface, dh: &DisplayHandle) -> Option<DrmNode> {
+    // buffers the client's GPU failed to import have to be copied from the GPU that imported them
+    let fallback = with_states(w, |states| {
+        states
+            .data_map
+            .get::<FallbackNode>()
+            .and_then(|node| *node.0.lock().unwrap())
+    });
+    if fallback.is_some() {
+        return fallback;
+    }
     // Lets check the global drm-node the client got either through default-feedback or wl_drm
     let client = dh.get_client(w.id()).ok()?;
     if let Some(normal_client) = client.get_data::<ClientState>() {
@@ -990,6 +1031,86 @@ fn render_node_for_output(
     }
 }
 
+/// Fails, if `dmabuf` is larger than the textures the renderer of `node` supports.
+fn check_texture_size(
+    renderer: &mut GlMultiRenderer<'_, '_>,
+    dmabuf: &Dmabuf,
+    node: &DrmNode,
+) -> Result<()> {
+    match max_texture_size(renderer) {
+        Ok(max_size) if !fits_texture_size(dmabuf, max_size) => {
+            anyhow::bail!("Dmabuf exceeds the maximum texture size of {}", max_size);
+        }
+        Err(err) => debug!(?err, ?node, "Failed to query the maximum texture size."),
+        Ok(_) => {}
+    }
+    Ok(())
+}
+
+/// Modifiers out of `supported` preferred for buffers of the format `code`.
+///
+/// Unless overridden by `config`, these are all supported modifiers except linear ones,
+/// as those are usually slow to render from on GPUs using tiled layouts.
+fn preferred_modifiers(
+    supported: impl IntoIterator<Item = Format>,
+    code: Fourcc,
+    config: &DmabufConfig,
+) -> Vec<Modifier> {
+    if !config.preferred_modifiers.is_empty() {
+        return config
+            .preferred_modifiers
+            .iter()
+            .map(|modifier| Modifier::from(*modifier))
+            .collect();
+    }
+    let supported = supported
+        .into_iter()
+        .filter(|format| format.code == code)
+        .map(|format| format.modifier)
+        .collect::<Vec<_>>();
+    let tiled = supported
+        .iter()
+        .copied()
+        .filter(|modifier| !matches!(modifier, Modifier::Linear | Modifier::Invalid))
+        .collect::<Vec<_>>();
+    if tiled.is_empty() {
+        supported
+    } else {
+        tiled
+    }
+}
+
+/// Formats out of `formats` advertised to clients.
+///
+/// With [`ModifierPolicy::Reject`] only preferred modifiers are advertised,
+/// unless that would leave no modifier at all for a format.
+fn advertised_formats(formats: &HashSet<Format>, config: &DmabufConfig) -> Vec<Format> {
+    if config.policy != ModifierPolicy::Reject {
+        return formats.iter().copied().collect();
+    }
+    let codes = formats
+        .iter()
+        .map(|format| format.code)
+        .collect::<HashSet<_>>();
+    codes
+        .into_iter()
+        .flat_map(|code| {
+            let preferred = preferred_modifiers(formats.iter().copied(), code, config);
+            let all = formats.iter().filter(move |format| format.code == code);
+            let filtered = all
+                .clone()
+                .filter(|format| preferred.contains(&format.modifier))
+                .copied()
+                .collect::<Vec<_>>();
+            if filtered.is_empty() {
+                all.copied().collect()
+            } else {
+                filtered
+            }
+        })
+        .collect()
+}
+
 fn get_surface_dmabuf_feedback(
     render_node: DrmNode,
     render_formats: HashSet<Format>,
@@ -1047,6 +1168,38 @@ fn get_surface_dmabuf_feedback(
     }
 }
 
+/// Reports if buffers of `format` can be scanned out by `compositor`.
+///
+/// Stricter than being importable, as the format also needs to be supported by the planes of its crtc.
+/// Formats missing from `render_formats` are never reported as scanout-capable,
+/// so that composition stays available as a fallback.
+fn scanout_support(
+    compositor: &GbmDrmCompositor,
+    render_formats: &HashSet<Format>,
+    format: Format,
+) -> ScanoutSupport {
+    if !render_formats.contains(&format) {
+        return ScanoutSupport::Unsupported;
+    }
+
+    let surface = compositor.surface();
+    let Ok(planes) = surface.planes() else {
+        return ScanoutSupport::Unsupported;
+    };
+    let supports = |plane| {
+        surface
+            .supported_formats(plane)
+            .map_or(false, |formats| formats.contains(&format))
+    };
+    if supports(planes.primary.handle) {
+        ScanoutSupport::Primary
+    } else if planes.overlay.iter().any(|plane| supports(plane.handle)) {
+        ScanoutSupport::Overlay
+    } else {
+        ScanoutSupport::Unsupported
+    }
+}
+
 impl Surface {
     pub fn render_output(
         &mut self,
@@ -1203,31 +1356,36 @@ impl Surface {
                     self.fps.screencopy();
                 }
 
-                state.send_frames(&self.output, &frame_result.states, |source_node| {
-                    Some(
-                        self.feedback
-                            .entry(source_node)
-                            .or_insert_with(|| {
-                                let render_formats = api
-                                    .single_renderer(&source_node)
-                                    .unwrap()
-                                    .dmabuf_formats()
-                                    .collect::<HashSet<_>>();
-                                let target_formats = api
-                                    .single_renderer(target_node)
-                                    .unwrap()
-                                    .dmabuf_formats()
-                                    .collect::<HashSet<_>>();
-                                get_surface_dmabuf_feedback(
-                                    source_node,
-                                    render_formats,
-                                    target_formats,
-                                    compositor,
-                                )
-                            })
-                            .clone(),
-                    )
-                });
+                let target_formats = api
+                    .single_renderer(target_node)
+                    .unwrap()
+                    .dmabuf_formats()
+                    .collect::<HashSet<_>>();
+                state.send_frames(
+                    &self.output,
+                    &frame_result.states,
+                    |source_node| {
+                        Some(
+                            self.feedback
+                                .entry(source_node)
+                                .or_insert_with(|| {
+                                    let render_formats = api
+                                        .single_renderer(&source_node)
+                                        .unwrap()
+                                        .dmabuf_formats()
+                                        .collect::<HashSet<_>>();
+                                    get_surface_dmabuf_feedback(
+                                        source_node,
+                                        render_formats,
+                                        target_formats.clone(),
+                                        compositor,
+                                    )
+                                })
+                                .clone(),
+                        )
+                    },
+                    |format| scanout_support(compositor, &target_formats, format),
+                );
             }
             Err(err) => {
                 compositor.reset_buffers();
@@ -1421,42 +1579,113 @@ impl KmsState {
         shell: &Shell,
     ) {
         let render = render_node_for_output(dh, &output, target, &shell);
-        if let Err(err) = self.api.early_import(
-            if let Some(client) = dh.get_client(surface.id()).ok() {
-                if let Some(normal_client) = client.get_data::<ClientState>() {
-                    normal_client.drm_node.clone()
-                } else if let Some(xwayland_client) = client.get_data::<XWaylandClientData>() {
-                    xwayland_client.user_data().get::<DrmNode>().cloned()
-                } else {
-                    None
-                }
-            } else {
-                None
-            },
-            render,
-            surface,
-        ) {
+        if let Err(err) =
+            self.api
+                .early_import(source_node_for_surface(surface, dh), render, surface)
+        {
             trace!(?err, "Early import failed.");
         }
     }
 
-    pub fn dmabuf_imported(&mut self, global: &DmabufGlobal, dmabuf: Dmabuf) -> Result<()> {
-        for device in self.devices.values() {
-            if device
-                .socket
-                .as_ref()
-                .map(|s| &s.dmabuf_global == global)
-                .unwrap_or(false)
-            {
-                return self
-                    .api
-                    .single_renderer(&device.render_node)?
-                    .import_dmabuf(&dmabuf, None)
-                    .map(|_| ())
-                    .map_err(Into::into);
+    /// Imports `dmabuf` into the renderer of the GPU `global` was advertised for.
+    ///
+    /// Buffers with a modifier that isn't preferred are logged once per format, if `config` says so.
+    /// If the import fails, the other GPUs are tried, as rendering copies buffers between GPUs as necessary.
+    /// The GPU that succeeded is remembered, see [`KmsState::record_fallback_node`].
+    pub fn dmabuf_imported(
+        &mut self,
+        global: &DmabufGlobal,
+        dmabuf: Dmabuf,
+        config: &DmabufConfig,
+    ) -> Result<()> {
+        let node = self
+            .devices
+            .values()
+            .find(|device| {
+                device
+                    .socket
+                    .as_ref()
+                    .map(|s| &s.dmabuf_global == global)
+                    .unwrap_or(false)
+            })
+            .map(|device| device.render_node)
+            .context("Dmabuf global doesn't belong to any GPU")?;
+
+        let mut renderer = self.api.single_renderer(&node)?;
+        let format = dmabuf.format();
+        // rejected modifiers aren't advertised, buffers using them anyway are imported as usual
+        if config.policy != ModifierPolicy::Accept && !self.warned_formats.contains(&format) {
+            let preferred = preferred_modifiers(renderer.dmabuf_formats(), format.code, config);
+            if !preferred.contains(&format.modifier) {
+                warn!(
+                    ?node,
+                    buffer = %describe_dmabuf(&dmabuf),
+                    ?preferred,
+                    "Client buffer uses a modifier, which isn't preferred."
+                );
+                self.warned_formats.insert(format);
+            }
+        }
+
+        check_texture_size(&mut renderer, &dmabuf, &node)?;
+        let err = match renderer.import_dmabuf(&dmabuf, None) {
+            Ok(_) => return Ok(()),
+            Err(err) => {
+                warn!(
+                    ?err,
+                    ?node,
+                    buffer = %describe_dmabuf(&dmabuf),
+                    supported = %supported_modifiers(&dmabuf, renderer.dmabuf_formats()),
+                    "Failed to import dmabuf."
+                );
+                err
+            }
+        };
+        std::mem::drop(renderer);
+
+        let other_nodes = self
+            .devices
+            .values()
+            .map(|device| device.render_node)
+            .filter(|other| other != &node)
+            .collect::<Vec<_>>();
+        for other in other_nodes {
+            let Ok(mut renderer) = self.api.single_renderer(&other) else {
+                continue;
+            };
+            if check_texture_size(&mut renderer, &dmabuf, &other).is_err() {
+                continue;
+            }
+            if renderer.import_dmabuf(&dmabuf, None).is_ok() {
+                debug!(?node, fallback = ?other, "Imported dmabuf on another GPU.");
+                self.fallback_imports.retain(|(weak, _)| !weak.is_gone());
+                self.fallback_imports.push((dmabuf.weak(), other));
+                return Ok(());
             }
         }
-        unreachable!()
+        Err(err.into())
+    }
+
+    /// Remembers the GPU `dmabuf` was imported on for `surface`, it is about to be attached to,
+    /// if the GPU of its global failed to import it. See [`source_node_for_surface`].
+    pub fn record_fallback_node(&self, surface: &WlSurface, dmabuf: &Dmabuf) {
+        let node = self
+            .fallback_imports
+            .iter()
+            .find(|(weak, _)| weak.upgrade().as_ref() == Some(dmabuf))
+            .map(|(_, node)| *node);
+        with_states(surface, |states| {
+            states
+                .data_map
+                .insert_if_missing_threadsafe(|| FallbackNode(Mutex::new(None)));
+            *states
+                .data_map
+                .get::<FallbackNode>()
+                .unwrap()
+                .0
+                .lock()
+                .unwrap() = node;
+        });
     }
 
     pub fn schedule_render(
@@ -47,17 +47,19 @@ use smithay::{
                 Element, Id, RenderElement,
             },
             gles::{
-                element::PixelShaderElement, GlesError, GlesPixelProgram, GlesRenderer, Uniform,
-                UniformName, UniformType,
+                element::PixelShaderElement, ffi, GlesError, GlesPixelProgram, GlesRenderer,
+                Uniform, UniformName, UniformType,
             },
             glow::GlowRenderer,
             multigpu::{gbm::GbmGlesBackend, Error as MultiError, MultiFrame, MultiRenderer},
             sync::SyncPoint,
+            utils::with_renderer_surface_state,
             Bind, Blit, ExportMem, ImportAll, ImportMem, Offscreen, Renderer, TextureFilter,
         },
     },
     desktop::{layer_map_for_output, PopupManager},
     output::{Output, OutputNoMode},
+    reexports::wayland_server::protocol::wl_shm,
     utils::{IsAlive, Logical, Point, Rectangle, Scale},
     wayland::{
         dmabuf::get_dmabuf,
@@ -82,9 +84,12 @@ pub static CLEAR_COLOR: [f32; 4] = [0.153, 0.161, 0.165, 1.0];
 pub static GROUP_COLOR: [f32; 3] = [0.788, 0.788, 0.788];
 pub static ACTIVE_GROUP_COLOR: [f32; 3] = [0.58, 0.922, 0.922];
 pub static FOCUS_INDICATOR_COLOR: [f32; 3] = [0.580, 0.921, 0.921];
+/// Variant of [`FOCUS_INDICATOR_COLOR`] for light backgrounds
+pub static FOCUS_INDICATOR_COLOR_DARK: [f32; 3] = [0.0, 0.361, 0.361];
 
 pub static OUTLINE_SHADER: &str = include_str!("./shaders/rounded_outline.frag");
 pub static RECTANGLE_SHADER: &str = include_str!("./shaders/rounded_rectangle.frag");
+pub static SHADOW_SHADER: &str = include_str!("./shaders/shadow.frag");
 
 pub struct IndicatorShader(pub GlesPixelProgram);
 
@@ -151,26 +156,55 @@ impl IndicatorShader {
     }
 
     pub fn focus_element<R: AsGlowRenderer>(
+        renderer: &R,
+        key: impl Into<Key>,
+        element_geo: Rectangle<i32, Logical>,
+        thickness: u8,
+        scale: f64,
+        alpha: f32,
+    ) -> PixelShaderElement {
+        IndicatorShader::focus_element_with_color(
+            renderer,
+            key,
+            element_geo,
+            thickness,
+            0,
+            scale,
+            alpha,
+            FOCUS_INDICATOR_COLOR,
+        )
+    }
+
+    /// Focus indicator around `element_geo`, following its corners if they are rounded by `corner_radius`.
+    ///
+    /// `thickness` is in logical pixels, `corner_radius` already in physical ones.
+    pub fn focus_element_with_color<R: AsGlowRenderer>(
         renderer: &R,
         key: impl Into<Key>,
         mut element_geo: Rectangle<i32, Logical>,
         thickness: u8,
+        corner_radius: u8,
         scale: f64,
         alpha: f32,
+        color: [f32; 3],
     ) -> PixelShaderElement {
         let t = thickness as i32;
         element_geo.loc -= (t, t).into();
         element_geo.size += (t * 2, t * 2).into();
 
+        // the shader takes the radius in physical pixels, so it has to grow with the scaled thickness
+        let physical_thickness = (thickness as f64 * scale).round().min(255.) as u8;
         IndicatorShader::element(
             renderer,
             key,
             element_geo,
             thickness,
-            thickness * 2,
+            physical_thickness
+                .saturating_mul(2)
+                .max(corner_radius.saturating_add(physical_thickness)),
             alpha,
             scale,
-            FOCUS_INDICATOR_COLOR,
+            color,
         )
     }
 
@@ -318,6 +352,94 @@ impl BackdropShader {
     }
 }
 
+pub struct ShadowShader(pub GlesPixelProgram);
+
+#[derive(PartialEq)]
+struct ShadowSettings {
+    radius: f32,
+    blur: f32,
+    alpha: f32,
+    color: [f32; 3],
+}
+type ShadowCache = RefCell<HashMap<Key, (ShadowSettings, PixelShaderElement)>>;
+
+impl ShadowShader {
+    pub fn get<R: AsGlowRenderer>(renderer: &R) -> GlesPixelProgram {
+        Borrow::<GlesRenderer>::borrow(renderer.glow_renderer())
+            .egl_context()
+            .user_data()
+            .get::<ShadowShader>()
+            .expect("Custom Shaders not initialized")
+            .0
+            .clone()
+    }
+
+    /// Shadow of a rectangle of `geo`, with `radius` rounded corners, fading out over `blur` pixels around it.
+    pub fn element<R: AsGlowRenderer>(
+        renderer: &R,
+        key: impl Into<Key>,
+        mut geo: Rectangle<i32, Logical>,
+        radius: f32,
+        blur: u8,
+        scale: f64,
+        alpha: f32,
+        color: [f32; 3],
+    ) -> PixelShaderElement {
+        let b = blur as i32;
+        geo.loc -= (b, b).into();
+        geo.size += (b * 2, b * 2).into();
+        let settings = ShadowSettings {
+            radius: radius * scale as f32,
+            blur: (blur as f64 * scale) as f32,
+            alpha,
+            color,
+        };
+
+        let user_data = Borrow::<GlesRenderer>::borrow(renderer.glow_renderer())
+            .egl_context()
+            .user_data();
+
+        user_data.insert_if_missing(|| ShadowCache::new(HashMap::new()));
+        let mut cache = user_data.get::<ShadowCache>().unwrap().borrow_mut();
+        cache.retain(|k, _| match k {
+            Key::Static(_) => true,
+            Key::Group(a) => a.upgrade().is_some(),
+            Key::Window(w) => w.alive(),
+        });
+
+        let key = key.into();
+        if cache
+            .get(&key)
+            .filter(|(old_settings, _)| &settings == old_settings)
+            .is_none()
+        {
+            let shader = Self::get(renderer);
+
+            let elem = PixelShaderElement::new(
+                shader,
+                geo,
+                None,
+                alpha,
+                vec![
+                    Uniform::new(
+                        "color",
+                        [color[0] * alpha, color[1] * alpha, color[2] * alpha],
+                    ),
+                    Uniform::new("radius", settings.radius),
+                    Uniform::new("blur", settings.blur),
+                ],
+            );
+            cache.insert(key.clone(), (settings, elem));
+        }
+
+        let elem = &mut cache.get_mut(&key).unwrap().1;
+        if elem.geometry(1.0.into()).to_logical(1) != geo {
+            elem.resize(geo, None);
+        }
+        elem.clone()
+    }
+}
+
 pub fn init_shaders<R: AsGlowRenderer>(renderer: &mut R) -> Result<(), GlesError> {
     let glow_renderer = renderer.glow_renderer_mut();
     let gles_renderer: &mut GlesRenderer = glow_renderer.borrow_mut();
@@ -338,6 +460,15 @@ pub fn init_shaders<R: AsGlowRenderer>(renderer: &mut R) -> Result<(), GlesError
         ],
     )?;
 
+    let shadow_shader = gles_renderer.compile_custom_pixel_shader(
+        SHADOW_SHADER,
+        &[
+            UniformName::new("color", UniformType::_3f),
+            UniformName::new("radius", UniformType::_1f),
+            UniformName::new("blur", UniformType::_1f),
+        ],
+    )?;
+
     let egl_context = gles_renderer.egl_context();
     egl_context
         .user_data()
@@ -345,10 +476,35 @@ pub fn init_shaders<R: AsGlowRenderer>(renderer: &mut R) -> Result<(), GlesError
     egl_context
         .user_data()
         .insert_if_missing(|| BackdropShader(rectangle_shader));
+    egl_context
+        .user_data()
+        .insert_if_missing(|| ShadowShader(shadow_shader));
 
     Ok(())
 }
 
+struct MaxTextureSize(i32);
+
+/// Largest width and height of textures `renderer` supports, queried once per context.
+pub fn max_texture_size<R: AsGlowRenderer>(renderer: &mut R) -> Result<i32, GlesError> {
+    let glow_renderer = renderer.glow_renderer_mut();
+    let gles_renderer: &mut GlesRenderer = glow_renderer.borrow_mut();
+
+    if let Some(MaxTextureSize(size)) = gles_renderer.egl_context().user_data().get() {
+        return Ok(*size);
+    }
+    let size = gles_renderer.with_context(|gl| {
+        let mut size = 0;
+        unsafe { gl.GetIntegerv(ffi::MAX_TEXTURE_SIZE, &mut size) };
+        size
+    })?;
+    gles_renderer
+        .egl_context()
+        .user_data()
+        .insert_if_missing(|| MaxTextureSize(size));
+    Ok(size)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CursorMode {
     None,
@@ -653,6 +809,72 @@ where
     Ok(elements)
 }
 
+/// Average relative luminance (0.0 - 1.0) of the wallpaper along the edges of `region`.
+///
+/// Only samples shm buffers of background layer surfaces, as reading back composed frames
+/// is too expensive to be done for every frame.
+pub fn background_luminance(output: &Output, region: Rectangle<i32, Logical>) -> Option<f32> {
+    const SAMPLES_PER_EDGE: i32 = 16;
+
+    let layer_map = layer_map_for_output(output);
+    let (layer_geo, buffer) = layer_map.layers_on(Layer::Background).find_map(|layer| {
+        let layer_geo = layer_map.layer_geometry(layer)?;
+        let buffer =
+            with_renderer_surface_state(layer.wl_surface(), |state| state.buffer().cloned())?;
+        Some((layer_geo, buffer))
+    })?;
+    if layer_geo.size.w <= 0 || layer_geo.size.h <= 0 {
+        return None;
+    }
+
+    let points = (0..SAMPLES_PER_EDGE).flat_map(|i| {
+        let (x, y) = (
+            region.loc.x + region.size.w * i / SAMPLES_PER_EDGE,
+            region.loc.y + region.size.h * i / SAMPLES_PER_EDGE,
+        );
+        let (right, bottom) = (
+            region.loc.x + region.size.w - 1,
+            region.loc.y + region.size.h - 1,
+        );
+        [
+            (x, region.loc.y),
+            (x, bottom),
+            (region.loc.x, y),
+            (right, y),
+        ]
+    });
+
+    with_buffer_contents(&buffer, |ptr, len, data| {
+        if !matches!(
+            data.format,
+            wl_shm::Format::Argb8888 | wl_shm::Format::Xrgb8888
+        ) {
+            return None;
+        }
+        let pixels = unsafe { std::slice::from_raw_parts(ptr, len) };
+        let (mut sum, mut count) = (0.0, 0);
+        for (x, y) in points {
+            if !layer_geo.contains((x, y)) {
+                continue;
+            }
+            let buffer_x = (x - layer_geo.loc.x) * data.width / layer_geo.size.w;
+            let buffer_y = (y - layer_geo.loc.y) * data.height / layer_geo.size.h;
+            let idx = (data.offset + buffer_y * data.stride + buffer_x * 4) as usize;
+            // little-endian, so the channels are stored as b, g, r, a
+            let Some([b, g, r, _]) = pixels.get(idx..idx + 4) else {
+                continue;
+            };
+            sum += 0.2126 * *r as f32 / 255.0
+                + 0.7152 * *g as f32 / 255.0
+                + 0.0722 * *b as f32 / 255.0;
+            count += 1;
+        }
+        (count > 0).then(|| sum / count as f32)
+    })
+    .ok()
+    .flatten()
+}
+
 pub fn split_layer_elements<R>(
     renderer: &mut R,
     output: &Output,
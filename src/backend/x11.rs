@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::{
-    backend::render,
+    backend::{kms::ScanoutSupport, render},
     config::OutputConfig,
     input::Devices,
     state::{BackendData, Common, Data},
@@ -252,7 +252,12 @@ impl Surface {
                     .with_context(|| "Failed to submit buffer for display")?;
                 #[cfg(feature = "debug")]
                 self.fps.displayed();
-                state.send_frames(&self.output, &states, |_| None);
+                state.send_frames(
+                    &self.output,
+                    &states,
+                    |_| None,
+                    |_| ScanoutSupport::Unsupported,
+                );
                 if damage.is_some() {
                     let mut output_presentation_feedback =
                         state.take_presentation_feedback(&self.output, &states);
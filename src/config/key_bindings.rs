@@ -1,7 +1,13 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::shell::{
-    focus::FocusDirection, grabs::ResizeEdge, layout::tiling::Direction, ResizeDirection,
+    focus::FocusDirection,
+    grabs::ResizeEdge,
+    layout::{
+        floating::{Axis, ScreenRegion, SortBy, WindowAction},
+        tiling::Direction,
+    },
+    ResizeDirection,
 };
 use serde::Deserialize;
 use smithay::{
@@ -151,7 +157,10 @@ pub enum Action {
     SendToPreviousOutput,
 
     Focus(FocusDirection),
+    /// Focuses the floating window following the focused one in the given order
+    CycleWindows(SortBy),
     Move(Direction),
+    MoveBy(i32, i32),
 
     ToggleOrientation,
     Orientation(crate::shell::layout::Orientation),
@@ -165,6 +174,25 @@ pub enum Action {
     #[serde(skip)]
     _ResizingInternal(ResizeDirection, ResizeEdge, KeyState),
     Maximize,
+    MaximizeAxis(Axis),
+    PlaceRegion(ScreenRegion),
+    UndoPlacement,
+    UndoGeometry,
+    ToggleDecorations,
+    ToggleSticky,
+    ToggleAlwaysOnTop,
+    /// Applies an action to all floating windows on the workspace of the same app as the focused one
+    AppWindows(WindowAction),
+    /// Raises the floating window under the pointer above all others, until triggered again
+    PeekWindow,
+    /// Saves the floating layout of the active workspace to the state directory
+    ExportLayout,
+    /// Rearranges the floating windows of the active workspace like the last exported layout
+    ImportLayout,
+    QuarterTile,
+    ClearSavedGeometry,
+    CenterWindow,
+    GatherWindows,
     Spawn(String),
 }
 
@@ -54,6 +54,319 @@ pub struct StaticConfig {
     pub active_hint: u8,
     #[serde(default = "default_gaps")]
     pub gaps: (u8, u8),
+    #[serde(default)]
+    pub floating: FloatingConfig,
+    #[serde(default)]
+    pub no_animations: Vec<String>,
+    /// App ids of windows, which are mapped normally even if they ask to start maximized or fullscreen
+    #[serde(default)]
+    pub ignore_initial_state: Vec<String>,
+    #[serde(default)]
+    pub resize_step: ResizeStepConfig,
+    /// Pointer speed (logical pixels per millisecond), above which an output the pointer
+    /// passes through isn't made active until the pointer settles (0 disables this)
+    #[serde(default)]
+    pub output_transit_velocity: f64,
+    #[serde(default)]
+    pub dmabuf: DmabufConfig,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct FloatingConfig {
+    /// Gap between floating windows and the output edges (top, right, bottom, left)
+    pub outer_gap: (u8, u8, u8, u8),
+    /// Don't apply the outer gap, if only a single window is on the output
+    pub smart_gaps: bool,
+    /// What happens when a floating window is dragged to the top edge of an output
+    pub top_edge_drag: TopEdgeDrag,
+    /// Distance from the left and right edges of an output, within which dropping a dragged
+    /// floating window snaps it to that half, or to a quarter near the corners (0 disables it)
+    pub edge_snap_threshold: u32,
+    /// Where new windows without a remembered position are placed
+    pub placement: WindowPlacement,
+    /// How large new windows without a remembered size may be initially
+    pub new_window_size: NewWindowSize,
+    /// Distance within which the edges of a dragged floating window stick to
+    /// the edges of other windows and of its output (0 disables it)
+    pub magnetic_edge_threshold: u32,
+    /// Size of the grid floating windows are aligned to, when moved or resized (0 disables it)
+    pub grid_size: u32,
+    /// Drop shadows behind floating windows
+    pub shadow: ShadowConfig,
+    /// Duration in milliseconds of the transition when maximizing or unmaximizing a floating window (0 disables it)
+    pub maximize_animation_ms: u64,
+    /// App ids of floating windows, which get highlighted on the first close and need a second one to confirm.
+    /// Focusing another window cancels the pending close.
+    pub confirm_close: Vec<String>,
+    /// Only send frame callbacks to unfocused windows once every n frames of their output (0 or 1 disables throttling)
+    pub unfocused_frame_throttle: u32,
+    /// Only send frame callbacks to fully covered windows once every n frames of their output (0 or 1 disables throttling)
+    pub occluded_frame_throttle: u32,
+    /// Seconds a closed window's geometry is kept around for a new window of the same app
+    pub closed_geometry_ttl: u64,
+    /// Smallest size floating windows can be resized to, regardless of the client's minimum size
+    pub min_window_size: (u32, u32),
+    /// App ids of terminals, which are hidden while a window of a program they started is open
+    pub swallow: Vec<String>,
+    /// When a floating window clicked into is raised.
+    ///
+    /// The window is focused on press regardless. Windows focused by other means,
+    /// like keyboard shortcuts or the pointer entering them, are always raised right away.
+    pub click_raise: ClickRaise,
+    /// Width of a border around floating windows, which unlike the focus indicator
+    /// doesn't overlap the window but takes up space next to it (0 disables it)
+    pub border_width: u8,
+    pub border_color: [f32; 3],
+    /// Corner radius of effects drawn on top of windows, like tints, unless set per window
+    pub corner_radius: u8,
+    /// Maximize windows into the largest area not covered by pinned windows, instead of the whole output
+    pub maximize_around_pinned: bool,
+    /// Margin kept between maximized windows and the output edges (0 fills the whole output)
+    pub maximize_gap: u8,
+    /// Alpha multiplier for unfocused floating windows, to emphasize the focused one (1.0 disables it)
+    pub inactive_dim: f32,
+    /// Switch to a darker focus indicator on light wallpapers, at the cost of sampling the wallpaper
+    pub adaptive_indicator_contrast: bool,
+    /// Move windows along with their output, when outputs are rearranged
+    pub follow_output_moves: bool,
+    /// What happens when moving focus past the last floating window in a direction
+    pub focus_wrap: FocusWrap,
+    /// Keep dragged windows within the output the drag started on
+    pub constrain_move_to_output: bool,
+    /// Keep windows resized with the keyboard within the output they are mostly on,
+    /// instead of all outputs they overlap
+    pub constrain_resize_to_output: bool,
+    /// App ids of windows, which open behind the focused window without taking focus
+    pub open_behind: Vec<String>,
+    /// App ids of windows, which open at the size they ask for, only limited by the work area
+    pub keep_initial_size: Vec<String>,
+    /// Opacity of the contents of windows, keyed by app id (1.0 is opaque)
+    pub app_opacity: HashMap<String, f32>,
+    /// App ids of windows drawing their own shadows, which don't get one from the compositor
+    pub no_shadow: Vec<String>,
+    /// What happens to a fullscreen window, when another window gets focused
+    pub fullscreen_focus_loss: FullscreenFocusLoss,
+    /// Flags of closed windows, which are re-applied to the next window of the same app
+    pub remember_flags: Vec<RememberedFlag>,
+    /// Save the geometry of closed windows per app, to open them there again after restarts
+    pub persist_geometry: bool,
+    /// Overrides of the settings above for single outputs, keyed by connector name
+    pub outputs: HashMap<String, OutputFloatingConfig>,
+    /// Connector names of the outputs new windows open on, keyed by app id.
+    ///
+    /// Windows open on the active output, if the configured one isn't connected.
+    pub app_outputs: HashMap<String, String>,
+}
+
+impl Default for FloatingConfig {
+    fn default() -> FloatingConfig {
+        FloatingConfig {
+            outer_gap: (0, 0, 0, 0),
+            smart_gaps: false,
+            top_edge_drag: TopEdgeDrag::Maximize,
+            edge_snap_threshold: 0,
+            placement: WindowPlacement::Center,
+            new_window_size: NewWindowSize::default(),
+            magnetic_edge_threshold: 0,
+            grid_size: 0,
+            shadow: ShadowConfig::default(),
+            maximize_animation_ms: 0,
+            confirm_close: Vec::new(),
+            unfocused_frame_throttle: 0,
+            occluded_frame_throttle: 0,
+            closed_geometry_ttl: 30,
+            min_window_size: (100, 100),
+            swallow: Vec::new(),
+            click_raise: ClickRaise::Press,
+            border_width: 0,
+            border_color: [0.5, 0.5, 0.5],
+            corner_radius: 8,
+            maximize_around_pinned: false,
+            maximize_gap: 0,
+            inactive_dim: 1.0,
+            adaptive_indicator_contrast: false,
+            follow_output_moves: true,
+            focus_wrap: FocusWrap::Stop,
+            constrain_move_to_output: false,
+            constrain_resize_to_output: false,
+            open_behind: Vec::new(),
+            keep_initial_size: Vec::new(),
+            app_opacity: HashMap::new(),
+            no_shadow: Vec::new(),
+            fullscreen_focus_loss: FullscreenFocusLoss::KeepFullscreen,
+            remember_flags: Vec::new(),
+            persist_geometry: false,
+            outputs: HashMap::new(),
+            app_outputs: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct ShadowConfig {
+    pub enabled: bool,
+    /// Offset of the shadow from the window
+    pub offset: (i32, i32),
+    /// Distance over which the shadow fades out
+    pub blur_radius: u8,
+    pub color: [f32; 3],
+    pub alpha: f32,
+    /// Alpha of the shadow behind the focused window
+    pub focused_alpha: f32,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> ShadowConfig {
+        ShadowConfig {
+            enabled: false,
+            offset: (0, 4),
+            blur_radius: 16,
+            color: [0.0, 0.0, 0.0],
+            alpha: 0.25,
+            focused_alpha: 0.45,
+        }
+    }
+}
+
+/// Floating settings of a single output, unset values follow the global [`FloatingConfig`]
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct OutputFloatingConfig {
+    pub outer_gap: Option<(u8, u8, u8, u8)>,
+    pub corner_radius: Option<u8>,
+    pub maximize_gap: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TopEdgeDrag {
+    #[default]
+    Maximize,
+    SnapTopHalf,
+    Nothing,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClickRaise {
+    #[default]
+    Press,
+    /// Raise once the button is released, also after dragging
+    Release,
+    /// Raise once the button is released, unless the pointer was dragged
+    ReleaseWithoutDrag,
+}
+
+/// Amount of pixels a keyboard resize changes a window by, growing while the key is held
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct ResizeStepConfig {
+    /// Step of the first resize
+    pub initial: i32,
+    /// Added to the step on every key repeat
+    pub increment: i32,
+    /// Factor the step is multiplied with on every key repeat (1.0 disables acceleration)
+    pub acceleration: f64,
+    /// Largest step reachable by holding the key
+    pub max: i32,
+}
+
+impl Default for ResizeStepConfig {
+    fn default() -> ResizeStepConfig {
+        ResizeStepConfig {
+            initial: 12,
+            increment: 2,
+            acceleration: 1.0,
+            max: 20,
+        }
+    }
+}
+
+impl ResizeStepConfig {
+    /// Step following `previous`, or the initial step for a new resize
+    pub fn next(&self, previous: Option<i32>) -> i32 {
+        match previous {
+            Some(previous) => {
+                let accelerated = (previous as f64 * self.acceleration.max(1.0)).round() as i32;
+                accelerated.saturating_add(self.increment).min(self.max)
+            }
+            None => self.initial.min(self.max),
+        }
+        .max(1)
+    }
+}
+
+/// Handling of client buffers with modifiers the renderer doesn't prefer, only applied on the KMS backend
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct DmabufConfig {
+    pub policy: ModifierPolicy,
+    /// DRM format modifiers considered optimal, if empty all non-linear modifiers
+    /// the renderer supports for the buffer's format are
+    pub preferred_modifiers: Vec<u64>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModifierPolicy {
+    /// Import any buffer the renderer supports
+    #[default]
+    Accept,
+    /// Import, but log the first buffer of every format with a modifier, which isn't preferred
+    Warn,
+    /// Only advertise preferred modifiers to clients, unless a format has none of them.
+    ///
+    /// Applies to gpus added after the change.
+    Reject,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowPlacement {
+    /// Center of the output
+    #[default]
+    Center,
+    /// Diagonally offset from the previously placed window, starting over in the top-left corner
+    Cascade,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum NewWindowSize {
+    /// Shrink windows larger than this fraction of the work area, like `Fraction(0.66)`
+    Fraction(f64),
+    /// Keep the size requested by the client
+    Natural,
+}
+
+impl Default for NewWindowSize {
+    fn default() -> NewWindowSize {
+        NewWindowSize::Fraction(2. / 3.)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FocusWrap {
+    /// Only move focus between windows on the same output
+    #[default]
+    Stop,
+    /// Continue on the opposite side of the output
+    Wrap,
+    /// Continue on adjacent outputs
+    CrossOutput,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FullscreenFocusLoss {
+    #[default]
+    KeepFullscreen,
+    /// Leave fullscreen and minimize the window. Tiled windows can't be minimized and only leave fullscreen.
+    Minimize,
+    Unfullscreen,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RememberedFlag {
+    Pinned,
+    Tint,
+    CornerRadius,
 }
 
 #[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
@@ -124,6 +437,13 @@ pub struct OutputConfig {
     pub enabled: bool,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub max_bpc: Option<u32>,
+    /// Insets (top, right, bottom, left) keeping windows clear of notches or rounded corners
+    #[serde(default, skip_serializing_if = "is_zero_insets")]
+    pub safe_area: (i32, i32, i32, i32),
+}
+
+fn is_zero_insets(insets: &(i32, i32, i32, i32)) -> bool {
+    *insets == (0, 0, 0, 0)
 }
 
 impl Default for OutputConfig {
@@ -136,6 +456,7 @@ impl Default for OutputConfig {
             position: (0, 0),
             enabled: true,
             max_bpc: None,
+            safe_area: (0, 0, 0, 0),
         }
     }
 }
@@ -221,6 +542,12 @@ impl Config {
             tiling_enabled: false,
             active_hint: default_active_hint(),
             gaps: default_gaps(),
+            floating: FloatingConfig::default(),
+            no_animations: Vec::new(),
+            ignore_initial_state: Vec::new(),
+            resize_step: ResizeStepConfig::default(),
+            output_transit_velocity: 0.0,
+            dmabuf: DmabufConfig::default(),
         }
     }
 
@@ -186,6 +186,34 @@ pub fn fps_ui(
                         ui.label(egui::RichText::new(format!("avg: {:>7.6}", avg)).code());
                         ui.label(egui::RichText::new(format!("min: {:>7.6}", min)).code());
                         ui.label(egui::RichText::new(format!("max: {:>7.6}", max)).code());
+                        let stats = &state.dmabuf_stats;
+                        for (backend, counter) in [
+                            ("kms", stats.kms),
+                            ("winit", stats.winit),
+                            ("x11", stats.x11),
+                        ] {
+                            if counter.succeeded + counter.failed > 0 {
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "dmabuf ({}): {} ok, {} failed ({:.1}%)",
+                                        backend,
+                                        counter.succeeded,
+                                        counter.failed,
+                                        counter.failure_ratio() * 100.0
+                                    ))
+                                    .code(),
+                                );
+                            }
+                        }
+                        if let Some(format) = stats.last_failure {
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "last failure: {:?} {:?}",
+                                    format.code, format.modifier
+                                ))
+                                .code(),
+                            );
+                        }
                         let elements_chart = BarChart::new(bars_elements).vertical();
                         let render_chart = BarChart::new(bars_render)
                             .stack_on(&[&elements_chart])
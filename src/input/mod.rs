@@ -4,16 +4,25 @@ use crate::{
     backend::render::cursor::CursorState,
     config::{xkb_config_to_wl, Action, Config, KeyPattern, WorkspaceLayout},
     shell::{
-        focus::{target::PointerFocusTarget, FocusDirection},
+        focus::{
+            target::{KeyboardFocusTarget, PointerFocusTarget},
+            FocusDirection,
+        },
         grabs::{ResizeEdge, SeatMoveGrabState},
-        layout::tiling::{Direction, FocusResult, MoveResult},
+        layout::{
+            floating::{GeometryStore, LayoutExport},
+            tiling::{Direction, FocusResult, MoveResult},
+        },
         OverviewMode, ResizeDirection, ResizeMode, Trigger, Workspace,
     },
-    state::Common,
+    state::{Common, Data},
     utils::prelude::*,
     wayland::{handlers::screencopy::ScreencopySessions, protocols::screencopy::Session},
 };
-use calloop::{timer::Timer, RegistrationToken};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle, RegistrationToken,
+};
 use cosmic_protocols::screencopy::v1::server::zcosmic_screencopy_session_v1::InputType;
 #[allow(deprecated)]
 use smithay::{
@@ -45,7 +54,7 @@ use tracing::{error, trace, warn};
 
 use std::{
     any::Any,
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::HashMap,
     time::{Duration, Instant},
 };
@@ -60,6 +69,17 @@ pub struct ActiveOutput(pub RefCell<Output>);
 pub struct SupressedKeys(RefCell<Vec<(u32, Option<RegistrationToken>)>>);
 #[derive(Default)]
 pub struct Devices(RefCell<HashMap<String, Vec<DeviceCapability>>>);
+/// Tracks how fast the pointer moves, to not activate outputs or focus surfaces it just passes through
+#[derive(Default)]
+pub struct PointerTransit {
+    last_motion: Cell<Option<u32>>,
+    settle: Cell<Option<RegistrationToken>>,
+    /// Pointer focus before the transit started
+    focus: RefCell<Option<(PointerFocusTarget, Point<i32, Logical>)>>,
+}
+
+/// Time the pointer has to rest, before the output it passed into quickly becomes active and focused
+const OUTPUT_TRANSIT_SETTLE: Duration = Duration::from_millis(150);
 
 impl Default for SeatId {
     fn default() -> SeatId {
@@ -98,6 +118,127 @@ impl SupressedKeys {
     }
 }
 
+impl PointerTransit {
+    /// Speed of a motion by `delta` at `time` in logical pixels per millisecond
+    fn velocity(&self, delta: Point<f64, Logical>, time: u32) -> f64 {
+        let elapsed = self
+            .last_motion
+            .replace(Some(time))
+            .map_or(u32::MAX, |last| time.wrapping_sub(last));
+        delta.x.hypot(delta.y) / elapsed.max(1) as f64
+    }
+
+    /// Whether the pointer of `seat` just passes through `output`, when moving with `velocity`
+    /// while `current_output` is active.
+    ///
+    /// Every motion during a transit restarts its deadline, so `output` only gets activated
+    /// once the pointer rests on it. Returning to `current_output` ends the transit.
+    fn in_transit(
+        &self,
+        loop_handle: &LoopHandle<'static, Data>,
+        seat: &Seat<State>,
+        current_output: &Output,
+        output: &Output,
+        velocity: f64,
+        threshold: f64,
+    ) -> bool {
+        if output == current_output {
+            self.cancel(loop_handle);
+            return false;
+        }
+        if self.settle.get().is_some() || (threshold > 0.0 && velocity > threshold) {
+            self.settle_later(loop_handle, seat);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Pointer focus for a motion over `under`, which keeps the previous focus while `in_transit`.
+    fn focus(
+        &self,
+        in_transit: bool,
+        under: Option<(PointerFocusTarget, Point<i32, Logical>)>,
+    ) -> Option<(PointerFocusTarget, Point<i32, Logical>)> {
+        if in_transit {
+            self.focus.borrow().clone()
+        } else {
+            *self.focus.borrow_mut() = under.clone();
+            under
+        }
+    }
+
+    /// Ends a pending transit without activating its output.
+    fn cancel(&self, loop_handle: &LoopHandle<'static, Data>) {
+        if let Some(token) = self.settle.take() {
+            loop_handle.remove(token);
+        }
+    }
+
+    /// Activates the output under the pointer of `seat` and focuses the surface under it,
+    /// once it stopped moving for a moment.
+    fn settle_later(&self, loop_handle: &LoopHandle<'static, Data>, seat: &Seat<State>) {
+        self.cancel(loop_handle);
+        let seat = seat.clone();
+        let token = loop_handle
+            .insert_source(
+                Timer::from_duration(OUTPUT_TRANSIT_SETTLE),
+                move |_, _, data| {
+                    let transit = seat.user_data().get::<PointerTransit>().unwrap();
+                    transit.settle.take();
+                    let ptr = seat.get_pointer().unwrap();
+                    let position = ptr.current_location();
+                    let current_output = seat.active_output();
+                    let Some(output) = data
+                        .state
+                        .common
+                        .shell
+                        .outputs()
+                        .find(|output| output.geometry().to_f64().contains(position))
+                        .cloned()
+                    else {
+                        return TimeoutAction::Drop;
+                    };
+                    if output != current_output {
+                        switch_active_output(&data.state.common, &seat, &current_output, &output);
+                    }
+
+                    // hand the pointer focus to the surface the pointer came to rest on
+                    let relative_pos = data
+                        .state
+                        .common
+                        .shell
+                        .map_global_to_space(position, &output);
+                    let overview = data.state.common.shell.overview_mode();
+                    let workspace = data.state.common.shell.workspaces.active_mut(&output);
+                    let under = State::surface_under(
+                        position,
+                        relative_pos,
+                        &output,
+                        output.geometry(),
+                        &data.state.common.shell.override_redirect_windows,
+                        overview,
+                        workspace,
+                    );
+                    let under = transit.focus(false, under);
+                    let time = Into::<Duration>::into(data.state.common.clock.now()).as_millis();
+                    ptr.motion(
+                        &mut data.state,
+                        under,
+                        &MotionEvent {
+                            location: position,
+                            serial: SERIAL_COUNTER.next_serial(),
+                            time: time as u32,
+                        },
+                    );
+                    TimeoutAction::Drop
+                },
+            )
+            .ok();
+        self.settle.set(token);
+    }
+}
+
 impl Devices {
     fn add_device<D: Device>(&self, device: &D) -> Vec<DeviceCapability> {
         let id = device.id();
@@ -142,6 +283,7 @@ pub fn add_seat(
     let userdata = seat.user_data();
     userdata.insert_if_missing(SeatId::default);
     userdata.insert_if_missing(Devices::default);
+    userdata.insert_if_missing(PointerTransit::default);
     userdata.insert_if_missing(SupressedKeys::default);
     userdata.insert_if_missing(SeatMoveGrabState::default);
     userdata.insert_if_missing(CursorState::default);
@@ -453,16 +595,19 @@ impl State {
                             .find(|output| output.geometry().to_f64().contains(position))
                             .cloned()
                             .unwrap_or(current_output.clone());
-                        if output != current_output {
-                            for session in sessions_for_output(&self.common, &current_output) {
-                                session.cursor_leave(seat, InputType::Pointer);
-                            }
-
-                            for session in sessions_for_output(&self.common, &output) {
-                                session.cursor_enter(seat, InputType::Pointer);
-                            }
-
-                            seat.set_active_output(&output);
+                        let transit = userdata.get::<PointerTransit>().unwrap();
+                        let velocity = transit.velocity(event.delta(), event.time_msec());
+                        // when just passing through, the output gets activated and focused once the pointer settles
+                        let in_transit = transit.in_transit(
+                            &self.common.event_loop_handle,
+                            seat,
+                            &current_output,
+                            &output,
+                            velocity,
+                            self.common.config.static_conf.output_transit_velocity,
+                        );
+                        if output != current_output && !in_transit {
+                            switch_active_output(&self.common, seat, &current_output, &output);
                         }
                         let output_geometry = output.geometry();
 
@@ -477,14 +622,17 @@ impl State {
                         let relative_pos = self.common.shell.map_global_to_space(position, &output);
                         let overview = self.common.shell.overview_mode();
                         let workspace = self.common.shell.workspaces.active_mut(&output);
-                        let under = State::surface_under(
-                            position,
-                            relative_pos,
-                            &output,
-                            output_geometry,
-                            &self.common.shell.override_redirect_windows,
-                            overview,
-                            workspace,
+                        let under = transit.focus(
+                            in_transit,
+                            State::surface_under(
+                                position,
+                                relative_pos,
+                                &output,
+                                output_geometry,
+                                &self.common.shell.override_redirect_windows,
+                                overview,
+                                workspace,
+                            ),
                         );
 
                         for session in sessions_for_output(&self.common, &output) {
@@ -547,18 +695,28 @@ impl State {
                                 &event,
                                 geometry.size,
                             );
+                        // absolute positions never leave the active output, which ends any transit
+                        let transit = userdata.get::<PointerTransit>().unwrap();
+                        transit.velocity(
+                            position - seat.get_pointer().unwrap().current_location(),
+                            event.time_msec(),
+                        );
+                        transit.cancel(&self.common.event_loop_handle);
                         let relative_pos = self.common.shell.map_global_to_space(position, &output);
                         let overview = self.common.shell.overview_mode();
                         let workspace = self.common.shell.workspaces.active_mut(&output);
                         let serial = SERIAL_COUNTER.next_serial();
-                        let under = State::surface_under(
-                            position,
-                            relative_pos,
-                            &output,
-                            geometry,
-                            &self.common.shell.override_redirect_windows,
-                            overview,
-                            workspace,
+                        let under = transit.focus(
+                            false,
+                            State::surface_under(
+                                position,
+                                relative_pos,
+                                &output,
+                                geometry,
+                                &self.common.shell.override_redirect_windows,
+                                overview,
+                                workspace,
+                            ),
                         );
 
                         for session in sessions_for_output(&self.common, &output) {
@@ -714,14 +872,26 @@ impl State {
                                         }
                                     }
                                 }
-                                Common::set_focus(
-                                    self,
-                                    under.and_then(|target| target.try_into().ok()).as_ref(),
-                                    seat,
-                                    Some(serial),
-                                );
+                                let target: Option<KeyboardFocusTarget> =
+                                    under.and_then(|target| target.try_into().ok());
+                                if let Some(KeyboardFocusTarget::Element(mapped)) = &target {
+                                    self.common
+                                        .shell
+                                        .active_space_mut(&output)
+                                        .floating_layer
+                                        .defer_raise(mapped, pos);
+                                }
+                                Common::set_focus(self, target.as_ref(), seat, Some(serial));
                             }
                         } else {
+                            let pos = seat.get_pointer().unwrap().current_location();
+                            for output in self.common.shell.outputs().cloned().collect::<Vec<_>>() {
+                                self.common
+                                    .shell
+                                    .active_space_mut(&output)
+                                    .floating_layer
+                                    .finish_deferred_raise(pos);
+                            }
                             if let OverviewMode::Started(Trigger::Pointer(action_button), _) =
                                 self.common.shell.overview_mode()
                             {
@@ -851,9 +1021,14 @@ impl State {
             }
             Action::Close => {
                 let current_output = seat.active_output();
-                let workspace = self.common.shell.active_space_mut(&current_output);
-                if let Some(window) = workspace.focus_stack.get(seat).last() {
-                    window.send_close();
+                let workspace = self.common.shell.active_space(&current_output);
+                let window = workspace
+                    .focus_stack
+                    .get(seat)
+                    .last()
+                    .map(|mapped| mapped.active_window());
+                if let Some(window) = window {
+                    self.common.shell.close_request(&window);
                 }
             }
             Action::Workspace(key_num) => {
@@ -1153,9 +1328,27 @@ impl State {
                 let current_output = seat.active_output();
                 let workspace = self.common.shell.active_space_mut(&current_output);
                 let focus_stack = workspace.focus_stack.get(seat);
-                let mut result = workspace
-                    .tiling_layer
-                    .next_focus(focus, seat, focus_stack.iter());
+                let floating_direction = match focus {
+                    FocusDirection::Left => Some(Direction::Left),
+                    FocusDirection::Right => Some(Direction::Right),
+                    FocusDirection::Up => Some(Direction::Up),
+                    FocusDirection::Down => Some(Direction::Down),
+                    FocusDirection::In | FocusDirection::Out => None,
+                };
+                let floating_focus = floating_direction
+                    .zip(focus_stack.last())
+                    .filter(|(_, focused)| workspace.floating_layer.mapped().any(|m| m == *focused))
+                    .and_then(|(direction, focused)| {
+                        workspace
+                            .floating_layer
+                            .focus_direction(focused, direction, &focus_stack)
+                    });
+                let mut result = match floating_focus {
+                    Some(mapped) => FocusResult::Some(KeyboardFocusTarget::from(mapped)),
+                    None => workspace
+                        .tiling_layer
+                        .next_focus(focus, seat, focus_stack.iter()),
+                };
                 if workspace.get_fullscreen(&current_output).is_some() {
                     result = FocusResult::None;
                 }
@@ -1213,6 +1406,26 @@ impl State {
                     }
                 }
             }
+            Action::CycleWindows(criterion) => {
+                let current_output = seat.active_output();
+                let workspace = self.common.shell.active_space(&current_output);
+                let focus_stack = workspace.focus_stack.get(seat);
+                let windows = workspace
+                    .floating_layer
+                    .windows_sorted_by(criterion, &focus_stack);
+                let next = windows
+                    .iter()
+                    .position(|(_, focused)| *focused)
+                    .map_or(0, |idx| idx + 1);
+                let target = windows
+                    .get(next)
+                    .or_else(|| windows.first())
+                    .map(|(mapped, _)| KeyboardFocusTarget::from((*mapped).clone()));
+                std::mem::drop(focus_stack);
+                if let Some(target) = target {
+                    Common::set_focus(self, Some(&target), seat, None);
+                }
+            }
             Action::Move(direction) => {
                 let current_output = seat.active_output();
                 let workspace = self.common.shell.active_space_mut(&current_output);
@@ -1281,9 +1494,145 @@ impl State {
                 let focus_stack = workspace.focus_stack.get(seat);
                 let focused_window = focus_stack.last();
                 if let Some(window) = focused_window.map(|f| f.active_window()) {
-                    workspace.maximize_toggle(&window, &current_output);
+                    workspace.toggle_maximize(&window, &current_output);
+                }
+            }
+            Action::MaximizeAxis(axis) => {
+                let current_output = seat.active_output();
+                let workspace = self.common.shell.active_space_mut(&current_output);
+                let focused = workspace.focus_stack.get(seat).last().cloned();
+                if let Some(window) = focused.map(|f| f.active_window()) {
+                    if workspace.floating_layer.is_maximized_axis(&window) {
+                        workspace.unmaximize_request(&window);
+                    } else {
+                        workspace.floating_layer.maximize_axis(&window, axis);
+                    }
+                }
+            }
+            Action::PlaceRegion(region) => {
+                let current_output = seat.active_output();
+                if let Some(focused) = seat.get_keyboard().unwrap().current_focus() {
+                    let workspace = self.common.shell.active_space_mut(&current_output);
+                    workspace.floating_layer.place_region(&focused, region);
+                }
+            }
+            Action::MoveBy(dx, dy) => {
+                let current_output = seat.active_output();
+                if let Some(focused) = seat.get_keyboard().unwrap().current_focus() {
+                    let workspace = self.common.shell.active_space_mut(&current_output);
+                    workspace.floating_layer.move_window(&focused, dx, dy);
+                }
+            }
+            Action::UndoPlacement => {
+                let current_output = seat.active_output();
+                if let Some(focused) = seat.get_keyboard().unwrap().current_focus() {
+                    let workspace = self.common.shell.active_space_mut(&current_output);
+                    workspace.floating_layer.undo_placement(&focused);
+                }
+            }
+            Action::UndoGeometry => {
+                let current_output = seat.active_output();
+                let workspace = self.common.shell.active_space_mut(&current_output);
+                let focused = workspace.focus_stack.get(seat).last().cloned();
+                if let Some(mapped) = focused {
+                    workspace.undo_geometry(&mapped);
+                }
+            }
+            Action::ToggleDecorations => {
+                let current_output = seat.active_output();
+                let workspace = self.common.shell.active_space_mut(&current_output);
+                let focused = workspace.focus_stack.get(seat).last().cloned();
+                if let Some(mapped) = focused {
+                    // client-side decorated windows get the server-side ones and vice versa
+                    let decorated = mapped.active_window().is_decorated(true);
+                    workspace.floating_layer.set_decorated(&mapped, decorated);
                 }
             }
+            Action::ToggleSticky => {
+                let current_output = seat.active_output();
+                let workspace = self.common.shell.active_space_mut(&current_output);
+                let focused = workspace.focus_stack.get(seat).last().cloned();
+                if let Some(mapped) = focused {
+                    let sticky = workspace.floating_layer.is_sticky(&mapped);
+                    workspace.floating_layer.set_sticky(&mapped, !sticky);
+                }
+            }
+            Action::ToggleAlwaysOnTop => {
+                let current_output = seat.active_output();
+                let workspace = self.common.shell.active_space_mut(&current_output);
+                let focused = workspace.focus_stack.get(seat).last().cloned();
+                if let Some(mapped) = focused {
+                    workspace
+                        .floating_layer
+                        .set_always_on_top(&mapped, !mapped.is_always_on_top());
+                }
+            }
+            Action::AppWindows(action) => {
+                let current_output = seat.active_output();
+                let workspace = self.common.shell.active_space_mut(&current_output);
+                let focused = workspace.focus_stack.get(seat).last().cloned();
+                if let Some(focused) = focused {
+                    let app_id = focused.active_window().app_id();
+                    workspace.floating_layer.for_each_matching(
+                        |mapped| mapped.active_window().app_id() == app_id,
+                        action,
+                    );
+                }
+            }
+            Action::PeekWindow => {
+                let current_output = seat.active_output();
+                let location = seat.get_pointer().unwrap().current_location()
+                    - current_output.geometry().loc.to_f64();
+                self.common
+                    .shell
+                    .active_space_mut(&current_output)
+                    .floating_layer
+                    .toggle_peek(location);
+            }
+            Action::ExportLayout => {
+                let current_output = seat.active_output();
+                self.common
+                    .shell
+                    .active_space(&current_output)
+                    .floating_layer
+                    .export()
+                    .save();
+            }
+            Action::ImportLayout => {
+                if let Some(layout) = LayoutExport::load() {
+                    let current_output = seat.active_output();
+                    self.common
+                        .shell
+                        .active_space_mut(&current_output)
+                        .floating_layer
+                        .import(&layout);
+                }
+            }
+            Action::QuarterTile => {
+                let current_output = seat.active_output();
+                let workspace = self.common.shell.active_space_mut(&current_output);
+                workspace.floating_layer.quarter_tile(&current_output);
+            }
+            Action::ClearSavedGeometry => GeometryStore::clear(),
+            Action::CenterWindow => {
+                let current_output = seat.active_output();
+                let workspace = self.common.shell.active_space_mut(&current_output);
+                let focused = workspace.focus_stack.get(seat).last().cloned();
+                if let Some(focused) = focused {
+                    workspace
+                        .floating_layer
+                        .center_request(&focused, &current_output);
+                }
+            }
+            Action::GatherWindows => {
+                let current_output = seat.active_output();
+                let shell = &mut self.common.shell;
+                shell
+                    .workspaces
+                    .active_mut(&current_output)
+                    .floating_layer
+                    .gather_to_output(&current_output, &mut shell.toplevel_info_state);
+            }
             Action::Resizing(direction) => self.common.shell.set_resize_mode(
                 Some((pattern, direction)),
                 &self.common.config,
@@ -1382,7 +1731,11 @@ impl State {
             {
                 return Some((or.clone().into(), or.geometry().loc));
             }
-            Some((window.clone().into(), output_geo.loc))
+            let window_loc = workspace
+                .fullscreen_geometry(output)
+                .map(|geo| geo.loc)
+                .unwrap_or_default();
+            Some((window.clone().into(), output_geo.loc + window_loc))
         } else {
             {
                 let layers = layer_map_for_output(output);
@@ -1428,6 +1781,18 @@ impl State {
     }
 }
 
+fn switch_active_output(state: &Common, seat: &Seat<State>, previous: &Output, output: &Output) {
+    for session in sessions_for_output(state, previous) {
+        session.cursor_leave(seat, InputType::Pointer);
+    }
+
+    for session in sessions_for_output(state, output) {
+        session.cursor_enter(seat, InputType::Pointer);
+    }
+
+    seat.set_active_output(output);
+}
+
 fn sessions_for_output(state: &Common, output: &Output) -> impl Iterator<Item = Session> {
     let workspace = state.shell.active_space(&output);
     let maybe_fullscreen = workspace.get_fullscreen(&output);
@@ -75,7 +75,12 @@ fn main() -> Result<()> {
                 client_compositor_state(&client).blocker_cleared(&mut data.state, &dh);
             }
         }
-        data.state.common.shell.refresh();
+        for output in data.state.common.shell.refresh() {
+            // picks up retitled stacks and re-evaluates the dmabuf feedback of moved windows
+            data.state
+                .backend
+                .schedule_render(&data.state.common.event_loop_handle, &output, None);
+        }
         state::Common::refresh_focus(&mut data.state);
 
         // send out events
@@ -85,6 +90,7 @@ fn main() -> Result<()> {
     // drop eventloop & state before logger
     std::mem::drop(event_loop);
     std::mem::drop(data);
+    shell::layout::floating::GeometryStore::flush();
 
     Ok(())
 }
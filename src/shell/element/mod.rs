@@ -42,10 +42,14 @@ use smithay::{
 };
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     fmt,
     hash::Hash,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
 };
 
 pub mod surface;
@@ -88,7 +92,19 @@ pub struct CosmicMapped {
     pub(super) tiling_node_id: Arc<Mutex<Option<NodeId>>>,
     //floating
     pub(super) last_geometry: Arc<Mutex<Option<Rectangle<i32, Logical>>>>,
+    pub(super) geometry_history: Arc<Mutex<VecDeque<Rectangle<i32, Logical>>>>,
     pub(super) resize_state: Arc<Mutex<Option<ResizeState>>>,
+    hidden: Arc<AtomicBool>,
+    last_frame: Arc<Mutex<Option<Duration>>>,
+    animations_disabled: Arc<AtomicBool>,
+    pinned: Arc<AtomicBool>,
+    always_on_top: Arc<AtomicBool>,
+    sticky: Arc<AtomicBool>,
+    borderless: Arc<AtomicBool>,
+    opacity: Arc<Mutex<f32>>,
+    tint: Arc<Mutex<Option<[f32; 4]>>>,
+    corner_radius: Arc<Mutex<Option<u8>>>,
+    shadow: Arc<Mutex<Option<bool>>>,
 
     #[cfg(feature = "debug")]
     debug: Arc<Mutex<Option<smithay_egui::EguiState>>>,
@@ -101,6 +117,7 @@ impl fmt::Debug for CosmicMapped {
             .field("last_cursor_position", &self.last_cursor_position)
             .field("tiling_node_id", &self.tiling_node_id)
             .field("resize_state", &self.resize_state)
+            .field("hidden", &self.hidden)
             .finish()
     }
 }
@@ -338,6 +355,117 @@ impl CosmicMapped {
         window.is_maximized(pending)
     }
 
+    /// Hides the element without unmapping it.
+    ///
+    /// Unlike minimizing, a hidden element keeps its place in the layout and stays
+    /// addressable, it just isn't rendered and doesn't receive pointer input.
+    pub fn set_hidden(&self, hidden: bool) {
+        self.hidden.store(hidden, Ordering::SeqCst);
+        if hidden {
+            self.set_activated(false);
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden.load(Ordering::SeqCst)
+    }
+
+    /// Marks the element as pinned, e.g. a docked sidebar other windows shouldn't cover when maximized.
+    pub fn set_pinned(&self, pinned: bool) {
+        self.pinned.store(pinned, Ordering::SeqCst);
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned.load(Ordering::SeqCst)
+    }
+
+    /// Keeps the element above all other floating windows, e.g. for picture-in-picture videos.
+    pub fn set_always_on_top(&self, always_on_top: bool) {
+        self.always_on_top.store(always_on_top, Ordering::SeqCst);
+    }
+
+    pub fn is_always_on_top(&self) -> bool {
+        self.always_on_top.load(Ordering::SeqCst)
+    }
+
+    /// Marks the element as sticky, following the active workspace instead of staying on one.
+    pub fn set_sticky(&self, sticky: bool) {
+        self.sticky.store(sticky, Ordering::SeqCst);
+    }
+
+    pub fn is_sticky(&self) -> bool {
+        self.sticky.load(Ordering::SeqCst)
+    }
+
+    /// Suppresses the border drawn around the element, independent of the configured width.
+    pub fn set_borderless(&self, borderless: bool) {
+        self.borderless.store(borderless, Ordering::SeqCst);
+    }
+
+    pub fn is_borderless(&self) -> bool {
+        self.borderless.load(Ordering::SeqCst)
+    }
+
+    /// Opacity of the window contents, independent of the alpha the element gets rendered with
+    pub fn set_opacity(&self, opacity: f32) {
+        *self.opacity.lock().unwrap() = opacity.clamp(0.0, 1.0);
+    }
+
+    pub fn opacity(&self) -> f32 {
+        *self.opacity.lock().unwrap()
+    }
+
+    pub fn set_animations_disabled(&self, disabled: bool) {
+        self.animations_disabled.store(disabled, Ordering::SeqCst);
+    }
+
+    pub fn animations_disabled(&self) -> bool {
+        self.animations_disabled.load(Ordering::SeqCst)
+    }
+
+    /// Sets a color (rgba) drawn over the whole element, e.g. to indicate some status.
+    pub fn set_tint(&self, tint: Option<[f32; 4]>) {
+        *self.tint.lock().unwrap() = tint;
+    }
+
+    pub fn tint(&self) -> Option<[f32; 4]> {
+        *self.tint.lock().unwrap()
+    }
+
+    /// Overrides the corner radius used for this element, e.g. for clients rounding their own corners.
+    /// `None` uses the default radius, `Some(0)` means square corners.
+    pub fn set_corner_radius(&self, radius: Option<u8>) {
+        *self.corner_radius.lock().unwrap() = radius;
+    }
+
+    pub fn corner_radius(&self) -> Option<u8> {
+        *self.corner_radius.lock().unwrap()
+    }
+
+    // TODO: add a `set_blur` override like this one, once the content behind windows can be blurred
+    /// Overrides whether a shadow is drawn behind this element, `None` follows the global setting.
+    pub fn set_shadow(&self, shadow: Option<bool>) {
+        *self.shadow.lock().unwrap() = shadow;
+    }
+
+    /// Whether a shadow should be drawn behind this element, given the global setting.
+    pub fn shadow(&self, default: bool) -> bool {
+        self.shadow.lock().unwrap().unwrap_or(default)
+    }
+
+    /// Whether the frame callbacks of a frame rendered at `time` should be held back,
+    /// because the last ones were sent less than `interval` ago.
+    ///
+    /// Held back callbacks stay queued and are sent with the next frame, that isn't skipped.
+    pub fn skip_frame(&self, time: Duration, interval: Duration) -> bool {
+        let mut last_frame = self.last_frame.lock().unwrap();
+        if matches!(*last_frame, Some(last) if time.saturating_sub(last) < interval) {
+            return true;
+        }
+        *last_frame = Some(time);
+        false
+    }
+
     pub fn set_activated(&self, activated: bool) {
         match &self.element {
             CosmicMappedInternal::Stack(s) => s.set_activate(activated),
@@ -425,6 +553,16 @@ impl CosmicMapped {
         }
     }
 
+    /// Whether the client doesn't allow resizing, by requesting equal min and max sizes.
+    pub fn is_fixed_size(&self) -> bool {
+        match (self.min_size(), self.max_size()) {
+            (Some(min_size), Some(max_size)) => {
+                min_size.w > 0 && min_size.h > 0 && min_size == max_size
+            }
+            _ => false,
+        }
+    }
+
     pub fn set_bounds(&self, size: impl Into<Option<Size<i32, Logical>>>) {
         let size = size.into();
         for (surface, _) in self.windows() {
@@ -446,6 +584,25 @@ impl CosmicMapped {
         }
     }
 
+    /// Applies location and size and sends both to the client in a single configure.
+    pub fn configure_geometry(&self, geo: Rectangle<i32, Logical>) -> Option<Serial> {
+        self.set_geometry(geo);
+
+        let active = self.active_window();
+        let mut serial = None;
+        for (window, _) in self.windows() {
+            // X11 surfaces are already configured by `set_geometry`
+            if matches!(window, CosmicSurface::X11(_)) {
+                continue;
+            }
+            let window_serial = window.send_configure();
+            if window == active {
+                serial = window_serial;
+            }
+        }
+        serial
+    }
+
     pub fn send_close(&self) {
         let window = match &self.element {
             CosmicMappedInternal::Stack(s) => s.active(),
@@ -477,6 +634,15 @@ impl CosmicMapped {
         }
     }
 
+    /// Height of the titlebar or tab bar drawn by us, 0 for client-side decorated windows
+    pub fn header_height(&self) -> i32 {
+        match &self.element {
+            CosmicMappedInternal::Stack(_) => stack::TAB_HEIGHT,
+            CosmicMappedInternal::Window(window) if window.has_ssd(false) => surface::SSD_HEIGHT,
+            _ => 0,
+        }
+    }
+
     pub fn stack_ref_mut(&mut self) -> Option<&mut CosmicStack> {
         match &mut self.element {
             CosmicMappedInternal::Stack(stack) => Some(stack),
@@ -754,6 +920,44 @@ impl CosmicMapped {
             popup_elements.into_iter().map(C::from).collect(),
         )
     }
+
+    /// Render elements of the server-side decorations only, i.e. the header bar or tab bar without any window contents.
+    ///
+    /// Empty for windows drawing their own decorations.
+    pub fn split_decoration_elements<R, C>(
+        &self,
+        renderer: &mut R,
+        location: smithay::utils::Point<i32, smithay::utils::Physical>,
+        scale: smithay::utils::Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C>
+    where
+        R: Renderer + ImportAll + ImportMem + AsGlowRenderer,
+        <R as Renderer>::TextureId: 'static,
+        CosmicMappedRenderElement<R>: RenderElement<R>,
+        C: From<CosmicMappedRenderElement<R>>,
+    {
+        let location = location
+            + self
+                .active_window()
+                .geometry()
+                .loc
+                .to_physical_precise_round(scale);
+        match &self.element {
+            CosmicMappedInternal::Stack(s) => s
+                .tab_bar_elements::<R, CosmicMappedRenderElement<R>>(
+                    renderer, location, scale, alpha,
+                ),
+            CosmicMappedInternal::Window(w) if w.has_ssd(false) => w
+                .header_elements::<R, CosmicMappedRenderElement<R>>(
+                    renderer, location, scale, alpha,
+                ),
+            _ => Vec::new(),
+        }
+        .into_iter()
+        .map(C::from)
+        .collect()
+    }
 }
 
 impl IsAlive for CosmicMapped {
@@ -936,7 +1140,19 @@ impl From<CosmicWindow> for CosmicMapped {
             last_cursor_position: Arc::new(Mutex::new(HashMap::new())),
             tiling_node_id: Arc::new(Mutex::new(None)),
             last_geometry: Arc::new(Mutex::new(None)),
+            geometry_history: Arc::new(Mutex::new(VecDeque::new())),
             resize_state: Arc::new(Mutex::new(None)),
+            hidden: Arc::new(AtomicBool::new(false)),
+            last_frame: Arc::new(Mutex::new(None)),
+            animations_disabled: Arc::new(AtomicBool::new(false)),
+            pinned: Arc::new(AtomicBool::new(false)),
+            always_on_top: Arc::new(AtomicBool::new(false)),
+            sticky: Arc::new(AtomicBool::new(false)),
+            borderless: Arc::new(AtomicBool::new(false)),
+            opacity: Arc::new(Mutex::new(1.0)),
+            tint: Arc::new(Mutex::new(None)),
+            corner_radius: Arc::new(Mutex::new(None)),
+            shadow: Arc::new(Mutex::new(None)),
             #[cfg(feature = "debug")]
             debug: Arc::new(Mutex::new(None)),
         }
@@ -950,7 +1166,19 @@ impl From<CosmicStack> for CosmicMapped {
             last_cursor_position: Arc::new(Mutex::new(HashMap::new())),
             tiling_node_id: Arc::new(Mutex::new(None)),
             last_geometry: Arc::new(Mutex::new(None)),
+            geometry_history: Arc::new(Mutex::new(VecDeque::new())),
             resize_state: Arc::new(Mutex::new(None)),
+            hidden: Arc::new(AtomicBool::new(false)),
+            last_frame: Arc::new(Mutex::new(None)),
+            animations_disabled: Arc::new(AtomicBool::new(false)),
+            pinned: Arc::new(AtomicBool::new(false)),
+            always_on_top: Arc::new(AtomicBool::new(false)),
+            sticky: Arc::new(AtomicBool::new(false)),
+            borderless: Arc::new(AtomicBool::new(false)),
+            opacity: Arc::new(Mutex::new(1.0)),
+            tint: Arc::new(Mutex::new(None)),
+            corner_radius: Arc::new(Mutex::new(None)),
+            shadow: Arc::new(Mutex::new(None)),
             #[cfg(feature = "debug")]
             debug: Arc::new(Mutex::new(None)),
         }
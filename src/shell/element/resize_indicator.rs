@@ -10,12 +10,15 @@ use crate::{
 use apply::Apply;
 use calloop::LoopHandle;
 use cosmic::{
-    iced::widget::{column, container, horizontal_space, row, vertical_space},
+    iced::{
+        alignment::{Horizontal, Vertical},
+        widget::{column, container, horizontal_space, row, vertical_space},
+    },
     iced_core::{Background, Color, Length},
     theme,
     widget::{icon, text},
 };
-use smithay::utils::Size;
+use smithay::utils::{Logical, Size};
 
 pub type ResizeIndicator = IcedElement<ResizeIndicatorInternal>;
 
@@ -27,6 +30,7 @@ pub fn resize_indicator(
     ResizeIndicator::new(
         ResizeIndicatorInternal {
             edges: Mutex::new(ResizeEdge::all()),
+            size: Mutex::new(Size::from((0, 0))),
             direction,
             shortcut1: config
                 .static_conf
@@ -54,16 +58,62 @@ pub fn resize_indicator(
 
 pub struct ResizeIndicatorInternal {
     pub edges: Mutex<ResizeEdge>,
+    /// Current size of the resized window, shown next to the resized edges
+    pub size: Mutex<Size<i32, Logical>>,
     pub direction: ResizeDirection,
     pub shortcut1: String,
     pub shortcut2: String,
 }
 
+impl ResizeIndicatorInternal {
+    /// Updates the displayed size, returns if it changed.
+    pub fn set_size(&self, size: Size<i32, Logical>) -> bool {
+        let mut current = self.size.lock().unwrap();
+        if *current != size {
+            *current = size;
+            true
+        } else {
+            false
+        }
+    }
+}
+
 impl Program for ResizeIndicatorInternal {
     type Message = ();
 
     fn view(&self) -> crate::utils::iced::Element<'_, Self::Message> {
         let edges = self.edges.lock().unwrap();
+        let size = *self.size.lock().unwrap();
+        // show the size close to the corner being dragged
+        let size_label = || {
+            text(format!("{} × {}", size.w, size.h))
+                .font(cosmic::font::FONT_SEMIBOLD)
+                .size(14)
+                .apply(container)
+                .padding([4, 12])
+                .style(theme::Container::custom(|theme| container::Appearance {
+                    text_color: Some(Color::from(theme.cosmic().accent.on)),
+                    background: Some(Background::Color(theme.cosmic().accent_color().into())),
+                    border_radius: 18.0.into(),
+                    border_width: 0.0,
+                    border_color: Color::TRANSPARENT,
+                }))
+                .apply(container)
+                .width(Length::Fill)
+                .padding(8)
+                .align_x(
+                    if edges.contains(ResizeEdge::LEFT) && !edges.contains(ResizeEdge::RIGHT) {
+                        Horizontal::Left
+                    } else if edges.contains(ResizeEdge::RIGHT) && !edges.contains(ResizeEdge::LEFT)
+                    {
+                        Horizontal::Right
+                    } else {
+                        Horizontal::Center
+                    },
+                )
+                .align_y(Vertical::Center)
+        };
+        let size_on_top = edges.contains(ResizeEdge::TOP) && !edges.contains(ResizeEdge::BOTTOM);
         column(vec![
             if edges.contains(ResizeEdge::TOP) {
                 icon(
@@ -120,44 +170,59 @@ impl Program for ResizeIndicatorInternal {
                 } else {
                     horizontal_space(36).into()
                 },
-                row(vec![
-                    text(&self.shortcut1)
-                        .font(cosmic::font::FONT_SEMIBOLD)
-                        .size(14)
-                        .into(),
-                    text(fl!("grow-window"))
-                        .font(cosmic::font::FONT)
-                        .size(14)
-                        .into(),
-                    horizontal_space(40).into(),
-                    text(&self.shortcut2)
-                        .font(cosmic::font::FONT_SEMIBOLD)
-                        .size(14)
-                        .into(),
-                    text(fl!("shrink-window"))
-                        .font(cosmic::font::FONT)
-                        .size(14)
-                        .into(),
+                column(vec![
+                    if size_on_top {
+                        size_label().into()
+                    } else {
+                        vertical_space(0).into()
+                    },
+                    row(vec![
+                        text(&self.shortcut1)
+                            .font(cosmic::font::FONT_SEMIBOLD)
+                            .size(14)
+                            .into(),
+                        text(fl!("grow-window"))
+                            .font(cosmic::font::FONT)
+                            .size(14)
+                            .into(),
+                        horizontal_space(40).into(),
+                        text(&self.shortcut2)
+                            .font(cosmic::font::FONT_SEMIBOLD)
+                            .size(14)
+                            .into(),
+                        text(fl!("shrink-window"))
+                            .font(cosmic::font::FONT)
+                            .size(14)
+                            .into(),
+                    ])
+                    .apply(container)
+                    .center_x()
+                    .center_y()
+                    .padding(16)
+                    .apply(container)
+                    .style(theme::Container::custom(|theme| container::Appearance {
+                        text_color: Some(Color::from(theme.cosmic().accent.on)),
+                        background: Some(Background::Color(theme.cosmic().accent_color().into())),
+                        border_radius: 18.0.into(),
+                        border_width: 0.0,
+                        border_color: Color::TRANSPARENT,
+                    }))
+                    .width(Length::Shrink)
+                    .height(Length::Shrink)
+                    .apply(container)
+                    .height(Length::Fill)
+                    .width(Length::Fill)
+                    .center_x()
+                    .center_y()
+                    .into(),
+                    if size_on_top {
+                        vertical_space(0).into()
+                    } else {
+                        size_label().into()
+                    },
                 ])
-                .apply(container)
-                .center_x()
-                .center_y()
-                .padding(16)
-                .apply(container)
-                .style(theme::Container::custom(|theme| container::Appearance {
-                    text_color: Some(Color::from(theme.cosmic().accent.on)),
-                    background: Some(Background::Color(theme.cosmic().accent_color().into())),
-                    border_radius: 18.0.into(),
-                    border_width: 0.0,
-                    border_color: Color::TRANSPARENT,
-                }))
-                .width(Length::Shrink)
-                .height(Length::Shrink)
-                .apply(container)
-                .height(Length::Fill)
                 .width(Length::Fill)
-                .center_x()
-                .center_y()
+                .height(Length::Fill)
                 .into(),
                 if edges.contains(ResizeEdge::RIGHT) {
                     icon(
@@ -105,7 +105,7 @@ impl CosmicStackInternal {
     }
 }
 
-const TAB_HEIGHT: i32 = 24;
+pub(super) const TAB_HEIGHT: i32 = 24;
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -369,6 +369,11 @@ impl CosmicStack {
         self.0.force_redraw()
     }
 
+    /// Redraws the tabs, e.g. after the title of one of the windows changed.
+    pub fn update_tabs(&self) {
+        self.0.force_redraw()
+    }
+
     pub fn surfaces(&self) -> impl Iterator<Item = CosmicSurface> {
         self.0.with_program(|p| {
             p.windows
@@ -521,6 +526,27 @@ impl CosmicStack {
             popup_elements.into_iter().map(C::from).collect(),
         )
     }
+
+    /// Render elements of the tab bar at `location`, without any windows.
+    pub(super) fn tab_bar_elements<R, C>(
+        &self,
+        renderer: &mut R,
+        location: smithay::utils::Point<i32, smithay::utils::Physical>,
+        scale: smithay::utils::Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: 'static,
+        C: From<CosmicStackRenderElement<R>>,
+    {
+        AsRenderElements::<R>::render_elements::<CosmicStackRenderElement<R>>(
+            &self.0, renderer, location, scale, alpha,
+        )
+        .into_iter()
+        .map(C::from)
+        .collect()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -607,8 +633,10 @@ impl Program for CosmicStackInternal {
                 }
             }
             Message::Close(idx) => {
-                if let Some(val) = self.windows.lock().unwrap().get(idx) {
-                    val.close()
+                if let Some(window) = self.windows.lock().unwrap().get(idx).cloned() {
+                    loop_handle.insert_idle(move |data| {
+                        data.state.common.shell.close_request(&window);
+                    });
                 }
             }
             Message::Scrolled => {
@@ -1034,6 +1062,12 @@ impl PointerTarget<State> for CosmicStack {
                                         workspace.element_geometry(stack_mapped).unwrap();
                                     let indicator_thickness =
                                         data.common.config.static_conf.active_hint;
+                                    let snap_threshold = data
+                                        .common
+                                        .config
+                                        .static_conf
+                                        .floating
+                                        .magnetic_edge_threshold;
                                     let was_tiled = workspace.is_tiled(stack_mapped);
 
                                     self.remove_idx(dragged_out);
@@ -1046,6 +1080,7 @@ impl PointerTarget<State> for CosmicStack {
                                         pos,
                                         pos.to_i32_round() - Point::from((elem_geo.size.w / 2, 24)),
                                         indicator_thickness,
+                                        snap_threshold,
                                         was_tiled,
                                     );
                                     if grab.is_tiling_grab() {
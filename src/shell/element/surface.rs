@@ -1,4 +1,7 @@
-use std::time::Duration;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
 use smithay::{
     backend::renderer::{
@@ -27,10 +30,10 @@ use smithay::{
                 shell::server::xdg_toplevel::State as ToplevelState,
             },
         },
-        wayland_server::protocol::wl_surface::WlSurface,
+        wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle, Resource},
     },
     space_elements,
-    utils::{user_data::UserDataMap, Logical, Rectangle, Serial, Size},
+    utils::{user_data::UserDataMap, Logical, Point, Rectangle, Serial, Size},
     wayland::{
         compositor::{with_states, SurfaceData},
         seat::WaylandFocus,
@@ -68,6 +71,8 @@ impl From<X11Surface> for CosmicSurface {
 
 pub const SSD_HEIGHT: i32 = 48;
 
+struct MinimizedState(AtomicBool);
+
 impl CosmicSurface {
     pub fn title(&self) -> String {
         match self {
@@ -89,6 +94,42 @@ impl CosmicSurface {
         }
     }
 
+    /// Whether `self` is a dialog or other transient window of `parent`
+    pub fn is_transient_for(&self, parent: &CosmicSurface) -> bool {
+        match (self, parent) {
+            (CosmicSurface::Wayland(window), CosmicSurface::Wayland(parent)) => {
+                with_states(window.toplevel().wl_surface(), |states| {
+                    states
+                        .data_map
+                        .get::<XdgToplevelSurfaceData>()
+                        .unwrap()
+                        .lock()
+                        .unwrap()
+                        .parent
+                        .as_ref()
+                        == Some(parent.toplevel().wl_surface())
+                })
+            }
+            (CosmicSurface::X11(surface), CosmicSurface::X11(parent)) => {
+                surface.is_transient_for() == Some(parent.window_id())
+            }
+            _ => false,
+        }
+    }
+
+    /// Position the client asked to be placed at in global coordinates, if any
+    ///
+    /// Only X11 clients can request a position, xdg toplevels are always placed by the compositor.
+    pub fn position_hint(&self) -> Option<Point<i32, Logical>> {
+        match self {
+            CosmicSurface::X11(surface) => {
+                let loc = surface.geometry().loc;
+                (loc != Point::from((0, 0))).then_some(loc)
+            }
+            _ => None,
+        }
+    }
+
     pub fn app_id(&self) -> String {
         match self {
             CosmicSurface::Wayland(window) => {
@@ -109,6 +150,20 @@ impl CosmicSurface {
         }
     }
 
+    /// Process id of the client owning this surface, if known.
+    pub fn pid(&self, dh: &DisplayHandle) -> Option<i32> {
+        match self {
+            CosmicSurface::Wayland(window) => window
+                .toplevel()
+                .wl_surface()
+                .client()
+                .and_then(|client| client.get_credentials(dh).ok())
+                .map(|credentials| credentials.pid),
+            CosmicSurface::X11(surface) => surface.pid().map(|pid| pid as i32),
+            _ => None,
+        }
+    }
+
     pub fn set_geometry(&self, geo: Rectangle<i32, Logical>) {
         match self {
             CosmicSurface::Wayland(window) => window
@@ -121,6 +176,16 @@ impl CosmicSurface {
         }
     }
 
+    /// Applies location and size and sends both to the client in a single configure.
+    pub fn configure_geometry(&self, geo: Rectangle<i32, Logical>) -> Option<Serial> {
+        self.set_geometry(geo);
+        match self {
+            // already configured by `set_geometry`
+            CosmicSurface::X11(_) => None,
+            _ => self.send_configure(),
+        }
+    }
+
     pub fn set_bounds(&self, size: impl Into<Option<Size<i32, Logical>>>) {
         match self {
             CosmicSurface::Wayland(window) => window.toplevel().with_pending_state(|state| {
@@ -130,6 +195,23 @@ impl CosmicSurface {
         }
     }
 
+    /// Marks the surface as minimized, which is only reported to clients and doesn't unmap it.
+    pub fn set_minimized(&self, minimized: bool) {
+        self.user_data()
+            .insert_if_missing_threadsafe(|| MinimizedState(AtomicBool::new(false)));
+        self.user_data()
+            .get::<MinimizedState>()
+            .unwrap()
+            .0
+            .store(minimized, Ordering::SeqCst);
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.user_data()
+            .get::<MinimizedState>()
+            .map_or(false, |state| state.0.load(Ordering::SeqCst))
+    }
+
     pub fn is_activated(&self, pending: bool) -> bool {
         match self {
             CosmicSurface::Wayland(window) => {
@@ -213,6 +295,26 @@ impl CosmicSurface {
         }
     }
 
+    /// Requests server- or client-side decorations, returns `false` if the surface can't be switched.
+    pub fn set_server_decorated(&self, enable: bool) -> bool {
+        match self {
+            CosmicSurface::Wayland(window) => {
+                let mode = if enable {
+                    DecorationMode::ServerSide
+                } else {
+                    DecorationMode::ClientSide
+                };
+                // keep the mode, when the window gets forced undecorated by a stack later
+                PreferredDecorationMode::update(window, Some(mode));
+                window.toplevel().with_pending_state(|pending| {
+                    pending.decoration_mode = Some(mode);
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub fn is_resizing(&self, pending: bool) -> Option<bool> {
         match self {
             CosmicSurface::Wayland(window) => {
@@ -411,6 +513,61 @@ impl CosmicSurface {
         })
     }
 
+    /// Allowed range of the client area's aspect ratio (width / height).
+    ///
+    /// Only X11 clients can ask for this, through `WM_NORMAL_HINTS`.
+    pub fn aspect_ratio(&self) -> Option<(f64, f64)> {
+        match self {
+            CosmicSurface::Wayland(_) => None,
+            CosmicSurface::X11(surface) => {
+                let (min, max) = surface.size_hints()?.aspect?;
+                let ratio = |numerator: i32, denominator: i32| {
+                    (numerator > 0 && denominator > 0)
+                        .then(|| numerator as f64 / denominator as f64)
+                };
+                let min = ratio(min.numerator, min.denominator)?;
+                let max = ratio(max.numerator, max.denominator)?;
+                (min <= max).then_some((min, max))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Shrinks `size` to fit the aspect ratio range requested by the client, if any.
+    ///
+    /// The size limits of the client take precedence over the aspect ratio.
+    pub fn constrain_aspect(&self, mut size: Size<i32, Logical>) -> Size<i32, Logical> {
+        let Some((min, max)) = self.aspect_ratio() else {
+            return size;
+        };
+        let ssd_height = if self.is_decorated(false) {
+            SSD_HEIGHT
+        } else {
+            0
+        };
+        let height = (size.h - ssd_height).max(1);
+        let ratio = size.w as f64 / height as f64;
+        if ratio < min {
+            size.h = (size.w as f64 / min).round() as i32 + ssd_height;
+        } else if ratio > max {
+            size.w = (height as f64 * max).round() as i32;
+        }
+
+        if let Some(min_size) = self.min_size() {
+            size.w = size.w.max(min_size.w);
+            size.h = size.h.max(min_size.h);
+        }
+        if let Some(max_size) = self.max_size() {
+            if max_size.w > 0 {
+                size.w = size.w.min(max_size.w);
+            }
+            if max_size.h > 0 {
+                size.h = size.h.min(max_size.h);
+            }
+        }
+        size
+    }
+
     pub fn serial_acked(&self, serial: &Serial) -> bool {
         match self {
             CosmicSurface::Wayland(window) => {
@@ -206,6 +206,27 @@ impl CosmicWindow {
             popup_elements.into_iter().map(C::from).collect(),
         )
     }
+
+    /// Render elements of the header bar at `location`, without the window.
+    pub(super) fn header_elements<R, C>(
+        &self,
+        renderer: &mut R,
+        location: smithay::utils::Point<i32, smithay::utils::Physical>,
+        scale: smithay::utils::Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: 'static,
+        C: From<CosmicWindowRenderElement<R>>,
+    {
+        AsRenderElements::<R>::render_elements::<CosmicWindowRenderElement<R>>(
+            &self.0, renderer, location, scale, alpha,
+        )
+        .into_iter()
+        .map(C::from)
+        .collect()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -252,14 +273,19 @@ impl Program for CosmicWindowInternal {
                                         .windows()
                                         .find(|(w, _)| w.wl_surface().as_ref() == Some(&surface))
                                         .unwrap();
-                                    workspace.maximize_request(&window, &output)
+                                    workspace.toggle_maximize(&window, &output)
                                 }
                             }
                         });
                     }
                 }
             }
-            Message::Close => self.window.close(),
+            Message::Close => {
+                let window = self.window.clone();
+                loop_handle.insert_idle(move |data| {
+                    data.state.common.shell.close_request(&window);
+                });
+            }
         }
         Command::none()
     }
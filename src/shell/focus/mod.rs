@@ -1,4 +1,5 @@
 use crate::{
+    config::FullscreenFocusLoss,
     shell::{element::CosmicMapped, Shell, Workspace},
     state::Common,
     utils::prelude::*,
@@ -96,6 +97,8 @@ impl Shell {
         active_seat: &Seat<State>,
         serial: Option<Serial>,
     ) {
+        let previous = ActiveFocus::get(active_seat);
+
         // update FocusStack and notify layouts about new focus (if any window)
         if let Some(KeyboardFocusTarget::Element(mapped)) = target {
             if let Some(workspace) = state.common.shell.space_for_mut(mapped) {
@@ -126,6 +129,47 @@ impl Shell {
                 serial.unwrap_or_else(|| SERIAL_COUNTER.next_serial()),
             );
         }
+
+        if let (
+            Some(KeyboardFocusTarget::Element(previous)),
+            Some(KeyboardFocusTarget::Element(mapped)),
+        ) = (previous, target)
+        {
+            if &previous != mapped {
+                // moving on to another window cancels a pending close confirmation
+                if let Some(workspace) = state.common.shell.space_for_mut(&previous) {
+                    for (window, _) in previous.windows() {
+                        workspace.floating_layer.cancel_close(&window);
+                    }
+                }
+                Shell::fullscreen_focus_lost(state, &previous);
+            }
+        }
+    }
+
+    fn fullscreen_focus_lost(state: &mut State, mapped: &CosmicMapped) {
+        let policy = state
+            .common
+            .config
+            .static_conf
+            .floating
+            .fullscreen_focus_loss;
+        if policy == FullscreenFocusLoss::KeepFullscreen {
+            return;
+        }
+        let Some(workspace) = state.common.shell.space_for_mut(mapped) else {
+            return;
+        };
+        let window = mapped.active_window();
+        if !window.is_fullscreen(true) || !workspace.fullscreen.values().any(|w| w == &window) {
+            return;
+        }
+
+        workspace.unfullscreen_request(&window);
+        if policy == FullscreenFocusLoss::Minimize {
+            // tiled windows can't be minimized, so they only leave fullscreen
+            workspace.minimize(mapped);
+        }
     }
 
     fn update_active<'a, 'b>(
@@ -160,7 +204,9 @@ impl Shell {
                         let _ = xwm.raise_window(&window);
                     }
                 }
-                if workspace.floating_layer.mapped().any(|m| m == focused) {
+                if workspace.floating_layer.mapped().any(|m| m == focused)
+                    && !workspace.floating_layer.is_raise_deferred(focused)
+                {
                     workspace.floating_layer.space.raise_element(focused, true);
                 }
             }
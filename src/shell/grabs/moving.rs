@@ -4,14 +4,16 @@ use crate::{
     backend::render::{
         cursor::{CursorShape, CursorState},
         element::AsGlowRenderer,
-        IndicatorShader,
+        BackdropShader, IndicatorShader, GROUP_COLOR,
     },
+    config::TopEdgeDrag,
     shell::{
         element::{
             stack_hover::{stack_hover, StackHover},
             CosmicMappedRenderElement,
         },
         focus::target::{KeyboardFocusTarget, PointerFocusTarget},
+        layout::floating::{align_to_grid, SnapTarget},
         CosmicMapped, CosmicSurface,
     },
     utils::prelude::*,
@@ -19,7 +21,7 @@ use crate::{
 
 use smithay::{
     backend::renderer::{
-        element::{utils::RescaleRenderElement, AsRenderElements, RenderElement},
+        element::{utils::RescaleRenderElement, AsRenderElements, Id, RenderElement},
         ImportAll, ImportMem, Renderer,
     },
     desktop::space::SpaceElement,
@@ -32,7 +34,7 @@ use smithay::{
     },
     output::Output,
     reexports::wayland_server::protocol::wl_surface::WlSurface,
-    utils::{IsAlive, Logical, Point, Rectangle, Scale, Serial},
+    utils::{IsAlive, Logical, Point, Rectangle, Scale, Serial, Size},
     wayland::compositor::SurfaceData,
 };
 use std::{
@@ -44,14 +46,27 @@ use std::{
 pub type SeatMoveGrabState = RefCell<Option<MoveGrabState>>;
 
 const RESCALE_ANIMATION_DURATION: f64 = 150.0;
+const TOP_EDGE_THRESHOLD: f64 = 8.0;
+
+/// What dropping the window at the current pointer location does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SnapAction {
+    Maximize,
+    TopHalf,
+    Edge(SnapTarget),
+}
 
 pub struct MoveGrabState {
     window: CosmicMapped,
     window_offset: Point<i32, Logical>,
+    /// Additional offset of the window, aligning its edges with nearby ones
+    magnet_offset: Point<i32, Logical>,
     indicator_thickness: u8,
     start: Instant,
     tiling: bool,
     stacking_indicator: Option<(StackHover, Point<i32, Logical>)>,
+    snap_preview: Option<(Output, SnapAction, Rectangle<i32, Logical>)>,
+    snap_preview_id: Id,
 }
 
 impl MoveGrabState {
@@ -65,7 +80,9 @@ impl MoveGrabState {
         #[cfg(feature = "debug")]
         puffin::profile_function!();
 
-        let scale = if self.tiling {
+        let scale = if self.tiling && self.window.animations_disabled() {
+            0.6
+        } else if self.tiling {
             0.6 + ((1.0
                 - (Instant::now().duration_since(self.start).as_millis() as f64
                     / RESCALE_ANIMATION_DURATION)
@@ -78,7 +95,7 @@ impl MoveGrabState {
         let cursor_at = seat.get_pointer().unwrap().current_location();
 
         let mut window_geo = self.window.geometry();
-        window_geo.loc += cursor_at.to_i32_round() + self.window_offset;
+        window_geo.loc += cursor_at.to_i32_round() + self.window_offset + self.magnet_offset;
         if !output.geometry().intersection(window_geo).is_some() {
             return Vec::new();
         }
@@ -86,8 +103,15 @@ impl MoveGrabState {
         let output_scale: Scale<f64> = output.current_scale().fractional_scale().into();
         let scaling_offset =
             self.window_offset - self.window_offset.to_f64().upscale(scale).to_i32_round();
-        let render_location =
-            cursor_at.to_i32_round() - output.geometry().loc + self.window_offset - scaling_offset;
+        let render_location = cursor_at.to_i32_round() - output.geometry().loc
+            + self.window_offset
+            + self.magnet_offset
+            - scaling_offset;
+        // only round in physical pixels, so the window follows the pointer smoothly on fractional scales
+        let physical_location = (cursor_at - output.geometry().loc.to_f64()
+            + (self.window_offset + self.magnet_offset - scaling_offset).to_f64())
+        .to_physical(output_scale)
+        .to_i32_round();
 
         let focus_element = if self.indicator_thickness > 0 {
             Some(
@@ -117,12 +141,31 @@ impl MoveGrabState {
             .window
             .split_render_elements::<R, CosmicMappedRenderElement<R>>(
                 renderer,
-                (render_location - self.window.geometry().loc)
-                    .to_physical_precise_round(output_scale),
+                physical_location
+                    - self
+                        .window
+                        .geometry()
+                        .loc
+                        .to_physical_precise_round(output_scale),
                 output_scale,
                 1.0,
             );
 
+        let snap_preview = self
+            .snap_preview
+            .as_ref()
+            .filter(|(preview_output, _, _)| preview_output == output)
+            .map(|(_, _, geometry)| {
+                CosmicMappedRenderElement::from(BackdropShader::element(
+                    renderer,
+                    self.snap_preview_id.clone(),
+                    *geometry,
+                    8.,
+                    0.4,
+                    GROUP_COLOR,
+                ))
+            });
+
         self.stacking_indicator
             .iter()
             .flat_map(|(indicator, location)| {
@@ -136,30 +179,19 @@ impl MoveGrabState {
             .chain(popup_elements)
             .chain(focus_element)
             .chain(window_elements.into_iter().map(|elem| match elem {
-                CosmicMappedRenderElement::Stack(stack) => {
-                    CosmicMappedRenderElement::GrabbedStack(
-                        RescaleRenderElement::from_element(
-                            stack,
-                            render_location.to_physical_precise_round(
-                                output.current_scale().fractional_scale(),
-                            ),
-                            scale,
-                        ),
-                    )
-                }
+                CosmicMappedRenderElement::Stack(stack) => CosmicMappedRenderElement::GrabbedStack(
+                    RescaleRenderElement::from_element(stack, physical_location, scale),
+                ),
                 CosmicMappedRenderElement::Window(window) => {
-                    CosmicMappedRenderElement::GrabbedWindow(
-                        RescaleRenderElement::from_element(
-                            window,
-                            render_location.to_physical_precise_round(
-                                output.current_scale().fractional_scale(),
-                            ),
-                            scale,
-                        ),
-                    )
+                    CosmicMappedRenderElement::GrabbedWindow(RescaleRenderElement::from_element(
+                        window,
+                        physical_location,
+                        scale,
+                    ))
                 }
                 x => x,
             }))
+            .chain(snap_preview)
             .map(I::from)
             .collect()
     }
@@ -186,8 +218,11 @@ pub struct MoveGrab {
     start_data: PointerGrabStartData<State>,
     seat: Seat<State>,
     cursor_output: Output,
+    start_output: Output,
     window_outputs: HashSet<Output>,
     tiling: bool,
+    /// Distance within which the window's edges stick to other edges (0 disables it)
+    snap_threshold: u32,
 }
 
 impl PointerGrab<State> for MoveGrab {
@@ -198,6 +233,30 @@ impl PointerGrab<State> for MoveGrab {
         _focus: Option<(PointerFocusTarget, Point<i32, Logical>)>,
         event: &MotionEvent,
     ) {
+        let mut event = event.clone();
+        if !self.tiling
+            && state
+                .common
+                .config
+                .static_conf
+                .floating
+                .constrain_move_to_output
+        {
+            let border = state
+                .common
+                .shell
+                .active_space(&self.start_output)
+                .floating_layer
+                .border_width(&self.window);
+            event.location = self.constrain_location(event.location, border);
+        }
+        let event = &event;
+        let magnet_offset = if self.tiling {
+            Point::from((0, 0))
+        } else {
+            self.magnet_offset(state, event.location)
+        };
+
         let Some(current_output) = state
             .common
             .shell
@@ -228,8 +287,10 @@ impl PointerGrab<State> for MoveGrab {
             .get::<SeatMoveGrabState>()
             .map(|s| s.borrow_mut());
         if let Some(grab_state) = borrow.as_mut().and_then(|s| s.as_mut()) {
+            grab_state.magnet_offset = magnet_offset;
             let mut window_geo = self.window.geometry();
-            window_geo.loc += event.location.to_i32_round() + grab_state.window_offset;
+            window_geo.loc +=
+                event.location.to_i32_round() + grab_state.window_offset + magnet_offset;
             for output in &state.common.shell.outputs {
                 if let Some(overlap) = output.geometry().intersection(window_geo) {
                     if self.window_outputs.insert(output.clone()) {
@@ -248,6 +309,46 @@ impl PointerGrab<State> for MoveGrab {
                 }
             }
 
+            if !self.tiling {
+                let location = handle.current_location();
+                let at_top_edge = at_top_edge(state, &current_output, location);
+
+                let action = match edge_snap_target(state, &current_output, location) {
+                    Some(target) => Some(SnapAction::Edge(target)),
+                    None if at_top_edge => {
+                        match state.common.config.static_conf.floating.top_edge_drag {
+                            TopEdgeDrag::Nothing => None,
+                            TopEdgeDrag::Maximize => Some(SnapAction::Maximize),
+                            TopEdgeDrag::SnapTopHalf => Some(SnapAction::TopHalf),
+                        }
+                    }
+                    None => None,
+                };
+                grab_state.snap_preview = action.map(|action| {
+                    let floating_layer = &state
+                        .common
+                        .shell
+                        .active_space(&current_output)
+                        .floating_layer;
+                    let geometry = match action {
+                        SnapAction::Edge(target) => floating_layer.region_geometry(
+                            &self.window,
+                            &current_output,
+                            target.region(),
+                        ),
+                        _ => {
+                            let mut geometry =
+                                floating_layer.work_area(&current_output, Some(&self.window));
+                            if action == SnapAction::TopHalf {
+                                geometry.size.h /= 2;
+                            }
+                            geometry
+                        }
+                    };
+                    (current_output.clone(), action, geometry)
+                });
+            }
+
             if self.tiling {
                 let indicator_location = state
                     .common
@@ -321,6 +422,7 @@ impl MoveGrab {
         initial_cursor_location: Point<f64, Logical>,
         initial_window_location: Point<i32, Logical>,
         indicator_thickness: u8,
+        snap_threshold: u32,
         was_tiled: bool,
     ) -> MoveGrab {
         let output = seat.active_output();
@@ -331,10 +433,13 @@ impl MoveGrab {
         let grab_state = MoveGrabState {
             window: window.clone(),
             window_offset: initial_window_location - initial_cursor_location.to_i32_round(),
+            magnet_offset: Point::from((0, 0)),
             indicator_thickness,
             start: Instant::now(),
             stacking_indicator: None,
             tiling: was_tiled,
+            snap_preview: None,
+            snap_preview_id: Id::new(),
         };
 
         *seat
@@ -353,8 +458,10 @@ impl MoveGrab {
             start_data,
             seat: seat.clone(),
             window_outputs: outputs,
-            cursor_output: output,
+            cursor_output: output.clone(),
+            start_output: output,
             tiling: was_tiled,
+            snap_threshold,
         }
     }
 
@@ -362,6 +469,165 @@ impl MoveGrab {
         self.tiling
     }
 
+    /// Restricts the pointer to where the grabbed window, including its `border`,
+    /// stays within the output the grab started on.
+    fn constrain_location(
+        &self,
+        location: Point<f64, Logical>,
+        border: i32,
+    ) -> Point<f64, Logical> {
+        let Some(window_offset) = self
+            .seat
+            .user_data()
+            .get::<SeatMoveGrabState>()
+            .and_then(|s| s.borrow().as_ref().map(|s| s.window_offset))
+        else {
+            return location;
+        };
+        let output_geo = self.start_output.geometry();
+        let size = self.window.geometry().size + Size::from((border * 2, border * 2));
+        let window_offset = window_offset - Point::from((border, border));
+
+        let clamp = |value: f64, start: i32, len: i32, window_len: i32, offset: i32| {
+            let (min, max) = (start - offset, start + len - window_len - offset);
+            let value = if min <= max {
+                value.clamp(min as f64, max as f64)
+            } else {
+                // the window is larger than the output
+                value
+            };
+            value.clamp(start as f64, (start + len - 1) as f64)
+        };
+        (
+            clamp(
+                location.x,
+                output_geo.loc.x,
+                output_geo.size.w,
+                size.w,
+                window_offset.x,
+            ),
+            clamp(
+                location.y,
+                output_geo.loc.y,
+                output_geo.size.h,
+                size.h,
+                window_offset.y,
+            ),
+        )
+            .into()
+    }
+
+    /// Offset of the window from where the pointer at `location` puts it, so that its edges
+    /// close to edges of other windows or of the output under the pointer line up.
+    /// Both axes snap independently. With a grid configured, the window is aligned to it instead.
+    fn magnet_offset(&self, state: &State, location: Point<f64, Logical>) -> Point<i32, Logical> {
+        let none = Point::from((0, 0));
+        let Some(window_offset) = self
+            .seat
+            .user_data()
+            .get::<SeatMoveGrabState>()
+            .and_then(|s| s.borrow().as_ref().map(|s| s.window_offset))
+        else {
+            return none;
+        };
+        // only the output under the pointer counts, even if others overlap it
+        let Some(output) = state
+            .common
+            .shell
+            .outputs()
+            .find(|output| output.geometry().contains(location.to_i32_round()))
+        else {
+            return none;
+        };
+        let output_geo = output.geometry();
+        let floating_layer = &state.common.shell.active_space(output).floating_layer;
+        let Some(space_loc) = floating_layer.space.output_geometry(output).map(|g| g.loc) else {
+            return none;
+        };
+
+        let grid = floating_layer.grid_size() as i32;
+        if grid > 0 {
+            // the grid takes precedence over the edges of other windows
+            let loc = location.to_i32_round() + window_offset - output_geo.loc;
+            return Point::from((
+                align_to_grid(loc.x, grid, f64::round) - loc.x,
+                align_to_grid(loc.y, grid, f64::round) - loc.y,
+            ));
+        }
+        if self.snap_threshold == 0 {
+            return none;
+        }
+
+        let threshold = self.snap_threshold as i32;
+        // edges snap including the borders, so these line up instead of overlapping
+        let with_border = |mut geo: Rectangle<i32, Logical>, elem: &CosmicMapped| {
+            let border = floating_layer.border_width(elem);
+            geo.loc -= Point::from((border, border));
+            geo.size += Size::from((border * 2, border * 2));
+            geo
+        };
+        let window_geo = with_border(
+            Rectangle::from_loc_and_size(
+                location.to_i32_round() + window_offset,
+                self.window.geometry().size,
+            ),
+            &self.window,
+        );
+        let windows = floating_layer
+            .space
+            .elements_for_output(output)
+            .filter(|elem| *elem != &self.window && !elem.is_hidden())
+            .filter_map(|elem| {
+                let mut geo = floating_layer.space.element_geometry(elem)?;
+                geo.loc += output_geo.loc - space_loc;
+                Some(with_border(geo, elem))
+            })
+            .collect::<Vec<_>>();
+
+        // smallest offset moving one of the window's edges onto one of the given edges
+        let snap = |start: i32, len: i32, edges: &mut dyn Iterator<Item = i32>| {
+            edges
+                .flat_map(|edge| [edge - start, edge - (start + len)])
+                .filter(|delta| delta.abs() <= threshold)
+                .min_by_key(|delta| delta.abs())
+        };
+        // windows only attract each other, if they are close on the other axis as well
+        let near = |start: i32, len: i32, other_start: i32, other_len: i32| {
+            start < other_start + other_len + threshold && other_start < start + len + threshold
+        };
+
+        let delta_x = snap(
+            window_geo.loc.x,
+            window_geo.size.w,
+            &mut [output_geo.loc.x, output_geo.loc.x + output_geo.size.w]
+                .into_iter()
+                .chain(
+                    windows
+                        .iter()
+                        .filter(|geo| {
+                            near(window_geo.loc.y, window_geo.size.h, geo.loc.y, geo.size.h)
+                        })
+                        .flat_map(|geo| [geo.loc.x, geo.loc.x + geo.size.w]),
+                ),
+        );
+        let delta_y = snap(
+            window_geo.loc.y,
+            window_geo.size.h,
+            &mut [output_geo.loc.y, output_geo.loc.y + output_geo.size.h]
+                .into_iter()
+                .chain(
+                    windows
+                        .iter()
+                        .filter(|geo| {
+                            near(window_geo.loc.x, window_geo.size.w, geo.loc.x, geo.size.w)
+                        })
+                        .flat_map(|geo| [geo.loc.y, geo.loc.y + geo.size.h]),
+                ),
+        );
+
+        (delta_x.unwrap_or(0), delta_y.unwrap_or(0)).into()
+    }
+
     fn ungrab(
         &mut self,
         state: &mut State,
@@ -381,7 +647,8 @@ impl MoveGrab {
             if grab_state.window.alive() {
                 let window_location = handle.current_location().to_i32_round()
                     - output.geometry().loc
-                    + grab_state.window_offset;
+                    + grab_state.window_offset
+                    + grab_state.magnet_offset;
 
                 let workspace_handle = state.common.shell.active_space(&output).handle;
                 for old_output in self.window_outputs.iter().filter(|o| *o != &output) {
@@ -419,23 +686,88 @@ impl MoveGrab {
                         .output_geometry(&output)
                         .unwrap()
                         .loc;
-                    grab_state.window.set_geometry(Rectangle::from_loc_and_size(
-                        window_location + offset,
-                        grab_state.window.geometry().size,
-                    ));
-                    state
-                        .common
-                        .shell
-                        .active_space_mut(&output)
-                        .floating_layer
-                        .map_internal(grab_state.window, &output, Some(window_location + offset));
-
+                    let snap = grab_state
+                        .snap_preview
+                        .filter(|(preview_output, _, _)| preview_output == &output);
+                    // a window snapped to the top half gets moved and resized in one configure
+                    if !matches!(snap, Some((_, SnapAction::TopHalf, _))) {
+                        grab_state.window.set_geometry(Rectangle::from_loc_and_size(
+                            window_location + offset,
+                            grab_state.window.geometry().size,
+                        ));
+                    }
                     let pointer_pos = handle.current_location();
                     let relative_pos = state.common.shell.map_global_to_space(pointer_pos, &output);
-                    Some((
-                        self.window.clone(),
-                        window_location + offset + (pointer_pos - relative_pos).to_i32_round(),
-                    ))
+                    let mut stacked = None;
+                    let workspace = state.common.shell.active_space_mut(&output);
+                    match snap {
+                        Some((_, SnapAction::TopHalf, geometry)) => {
+                            let geometry = workspace.floating_layer.shrink_by_border(geometry);
+                            workspace
+                                .floating_layer
+                                .place(grab_state.window, &output, geometry);
+                        }
+                        Some((_, SnapAction::Edge(target), _)) => {
+                            let mapped = grab_state.window.clone();
+                            workspace.floating_layer.map_internal(
+                                grab_state.window,
+                                &output,
+                                Some(window_location + offset),
+                            );
+                            workspace.floating_layer.snap_to(&mapped, &output, target);
+                        }
+                        Some((_, SnapAction::Maximize, _)) => {
+                            let surface = grab_state.window.active_window();
+                            workspace.floating_layer.map_internal(
+                                grab_state.window,
+                                &output,
+                                Some(window_location + offset),
+                            );
+                            workspace.maximize_request(&surface, &output);
+                        }
+                        _ => {
+                            // dropping onto the titlebar of another window stacks them
+                            if let Some(target) =
+                                workspace.floating_layer.header_under(relative_pos).cloned()
+                            {
+                                stacked = workspace.stack_with(&target, &grab_state.window);
+                            }
+                            if stacked.is_none() {
+                                let mapped = grab_state.window.clone();
+                                workspace.floating_layer.map_internal(
+                                    grab_state.window,
+                                    &output,
+                                    Some(window_location + offset),
+                                );
+                                if grab_state.magnet_offset == Point::from((0, 0)) {
+                                    let precise = handle.current_location()
+                                        - output.geometry().loc.to_f64()
+                                        + (grab_state.window_offset + offset).to_f64();
+                                    workspace
+                                        .floating_layer
+                                        .set_precise_location(&mapped, precise);
+                                }
+                            }
+                        }
+                    }
+
+                    match stacked {
+                        Some(stack) => {
+                            let location = workspace
+                                .floating_layer
+                                .element_geometry(&stack)
+                                .unwrap()
+                                .loc;
+                            Some((
+                                stack,
+                                location + (pointer_pos - relative_pos).to_i32_round(),
+                            ))
+                        }
+                        None => Some((
+                            self.window.clone(),
+                            window_location + offset + (pointer_pos - relative_pos).to_i32_round(),
+                        )),
+                    }
                 }
             } else {
                 None
@@ -456,7 +788,7 @@ impl MoveGrab {
                 state,
                 Some((
                     PointerFocusTarget::from(mapped.clone()),
-                    position - self.window.geometry().loc,
+                    position - mapped.geometry().loc,
                 )),
                 &MotionEvent {
                     location: handle.current_location(),
@@ -473,3 +805,68 @@ impl MoveGrab {
         }
     }
 }
+
+/// Half or quarter of `output` a window dropped with the pointer at `location` snaps to.
+/// Whether `location` is close enough to the top edge of `output`, to maximize or snap a dragged window.
+///
+/// Like the side edges in [`edge_snap_target`], a top edge shared with another output doesn't count.
+fn at_top_edge(state: &State, output: &Output, location: Point<f64, Logical>) -> bool {
+    let geo = output.geometry().to_f64();
+    if location.y - geo.loc.y >= TOP_EDGE_THRESHOLD {
+        return false;
+    }
+
+    let beyond = Point::from((location.x, geo.loc.y - 1.0));
+    !state
+        .common
+        .shell
+        .outputs()
+        .any(|o| o != output && o.geometry().to_f64().contains(beyond))
+}
+
+fn edge_snap_target(
+    state: &State,
+    output: &Output,
+    location: Point<f64, Logical>,
+) -> Option<SnapTarget> {
+    let threshold = state.common.config.static_conf.floating.edge_snap_threshold as f64;
+    if threshold <= 0.0 {
+        return None;
+    }
+    let geo = output.geometry().to_f64();
+    let left = location.x - geo.loc.x < threshold;
+    let right = geo.loc.x + geo.size.w - location.x <= threshold;
+    if !left && !right {
+        return None;
+    }
+
+    // edges shared with another output are for moving windows over to it
+    let beyond = Point::from((
+        if left {
+            geo.loc.x - 1.0
+        } else {
+            geo.loc.x + geo.size.w + 1.0
+        },
+        location.y,
+    ));
+    if state
+        .common
+        .shell
+        .outputs()
+        .any(|o| o != output && o.geometry().to_f64().contains(beyond))
+    {
+        return None;
+    }
+
+    let corner = geo.size.h / 4.0;
+    let top = location.y - geo.loc.y < corner;
+    let bottom = geo.loc.y + geo.size.h - location.y <= corner;
+    Some(match (left, top, bottom) {
+        (true, true, _) => SnapTarget::TopLeft,
+        (true, _, true) => SnapTarget::BottomLeft,
+        (true, _, _) => SnapTarget::Left,
+        (false, true, _) => SnapTarget::TopRight,
+        (false, _, true) => SnapTarget::BottomRight,
+        (false, _, _) => SnapTarget::Right,
+    })
+}
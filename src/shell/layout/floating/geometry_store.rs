@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use smithay::utils::{Logical, Rectangle};
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    path::Path,
+    sync::{
+        mpsc::{channel, Sender},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use tracing::{error, warn};
+
+/// Time to wait for further changes, before writing the store to disk
+const PERSIST_DELAY: Duration = Duration::from_secs(1);
+
+static STORE: Lazy<Mutex<GeometryStore>> = Lazy::new(|| Mutex::new(GeometryStore::load()));
+
+/// Geometry of the last closed window of an app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedGeometry {
+    /// Connector name of the output the window was mostly shown on
+    pub output: String,
+    /// Output-local location and size as `(x, y, width, height)`
+    pub geometry: (i32, i32, i32, i32),
+}
+
+impl SavedGeometry {
+    pub fn new(output: String, geometry: Rectangle<i32, Logical>) -> SavedGeometry {
+        SavedGeometry {
+            output,
+            geometry: (
+                geometry.loc.x,
+                geometry.loc.y,
+                geometry.size.w,
+                geometry.size.h,
+            ),
+        }
+    }
+
+    pub fn geometry(&self) -> Rectangle<i32, Logical> {
+        let (x, y, w, h) = self.geometry;
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+}
+
+/// Window geometry per app id, persisted in the state directory across restarts
+#[derive(Debug, Default)]
+pub struct GeometryStore {
+    /// Hands snapshots of `apps` to the thread writing them to disk
+    writer: Option<Sender<HashMap<String, SavedGeometry>>>,
+    writer_thread: Option<JoinHandle<()>>,
+    apps: HashMap<String, SavedGeometry>,
+}
+
+impl GeometryStore {
+    fn load() -> GeometryStore {
+        let path = xdg::BaseDirectories::new().ok().and_then(|base| {
+            base.place_state_file("cosmic-comp/window_geometry.ron")
+                .ok()
+        });
+        let apps = path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                match ron::de::from_reader(OpenOptions::new().read(true).open(path).ok()?) {
+                    Ok(apps) => Some(apps),
+                    Err(err) => {
+                        warn!(?err, "Failed to read saved window geometry, resetting..");
+                        if let Err(err) = std::fs::remove_file(path) {
+                            error!(?err, "Failed to remove saved window geometry.");
+                        }
+                        None
+                    }
+                }
+            })
+            .unwrap_or_default();
+
+        let (writer, writer_thread) = path
+            .and_then(|path| {
+                let (tx, rx) = channel::<HashMap<String, SavedGeometry>>();
+                thread::Builder::new()
+                    .name("geometry-store".into())
+                    .spawn(move || {
+                        while let Ok(mut apps) = rx.recv() {
+                            // coalesce bursts of changes, e.g. when closing many windows at once,
+                            // but write right away, once the store gets flushed
+                            let deadline = Instant::now() + PERSIST_DELAY;
+                            while let Ok(newer) =
+                                rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                            {
+                                apps = newer;
+                            }
+                            write(&path, &apps);
+                        }
+                    })
+                    .map_err(|err| {
+                        error!(?err, "Failed to spawn thread persisting window geometry.")
+                    })
+                    .ok()
+                    .map(|thread| (Some(tx), Some(thread)))
+            })
+            .unwrap_or_default();
+
+        GeometryStore {
+            writer,
+            writer_thread,
+            apps,
+        }
+    }
+
+    fn persist(&self) {
+        if let Some(writer) = self.writer.as_ref() {
+            let _ = writer.send(self.apps.clone());
+        }
+    }
+
+    pub fn get(app_id: &str) -> Option<SavedGeometry> {
+        STORE.lock().unwrap().apps.get(app_id).cloned()
+    }
+
+    pub fn save(app_id: String, saved: SavedGeometry) {
+        let mut store = STORE.lock().unwrap();
+        if store.apps.get(&app_id) != Some(&saved) {
+            store.apps.insert(app_id, saved);
+            store.persist();
+        }
+    }
+
+    /// Forgets the geometry of all apps
+    pub fn clear() {
+        let mut store = STORE.lock().unwrap();
+        store.apps.clear();
+        store.persist();
+    }
+
+    /// Waits for pending changes to be written to disk, e.g. before exiting.
+    ///
+    /// Changes made afterwards aren't persisted anymore.
+    pub fn flush() {
+        if let Some(store) = Lazy::get(&STORE) {
+            std::mem::drop(std::mem::take(&mut *store.lock().unwrap()));
+        }
+    }
+}
+
+impl Drop for GeometryStore {
+    fn drop(&mut self) {
+        // closing the channel makes the thread write the last snapshot right away and exit
+        self.writer.take();
+        if let Some(thread) = self.writer_thread.take() {
+            if thread.join().is_err() {
+                error!("Thread persisting window geometry panicked.");
+            }
+        }
+    }
+}
+
+/// Writes `apps` to `path`, replacing its previous content.
+fn write(path: &Path, apps: &HashMap<String, SavedGeometry>) {
+    let writer = match OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path)
+    {
+        Ok(writer) => writer,
+        Err(err) => {
+            warn!(?err, "Failed to persist {}.", path.display());
+            return;
+        }
+    };
+    if let Err(err) = ron::ser::to_writer_pretty(writer, apps, Default::default()) {
+        warn!(?err, "Failed to persist {}", path.display());
+    }
+}
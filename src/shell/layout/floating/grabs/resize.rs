@@ -14,6 +14,7 @@ use smithay::{
     },
     utils::{IsAlive, Logical, Point, Rectangle, Size},
 };
+use tracing::warn;
 
 /// Information about the resize operation.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -41,6 +42,9 @@ pub struct ResizeSurfaceGrab {
     edges: ResizeEdge,
     initial_window_size: Size<i32, Logical>,
     last_window_size: Size<i32, Logical>,
+    min_size_floor: Size<i32, Logical>,
+    /// Width divided by height, which is kept while resizing
+    aspect_ratio: Option<f64>,
 }
 
 impl PointerGrab<State> for ResizeSurfaceGrab {
@@ -84,27 +88,65 @@ impl PointerGrab<State> for ResizeSurfaceGrab {
             new_window_height = (self.initial_window_size.h as f64 + dy) as i32;
         }
 
+        if let Some(ratio) = self.aspect_ratio {
+            // the edge moved relatively further drives the size in corners
+            let scale_w = new_window_width as f64 / self.initial_window_size.w.max(1) as f64;
+            let scale_h = new_window_height as f64 / self.initial_window_size.h.max(1) as f64;
+            let width_drives = !self.edges.intersects(top_bottom)
+                || (self.edges.intersects(left_right)
+                    && (scale_w - 1.0).abs() >= (scale_h - 1.0).abs());
+            if width_drives {
+                new_window_height = (new_window_width as f64 / ratio).round() as i32;
+            } else {
+                new_window_width = (new_window_height as f64 * ratio).round() as i32;
+            }
+        }
+
         let (min_size, max_size) = (self.window.min_size(), self.window.max_size());
 
-        let min_width = min_size.map(|s| s.w).unwrap_or(360);
-        let min_height = min_size.map(|s| s.h).unwrap_or(240);
+        let min_width = min_size
+            .map(|s| s.w)
+            .unwrap_or(360)
+            .max(self.min_size_floor.w);
+        let min_height = min_size
+            .map(|s| s.h)
+            .unwrap_or(240)
+            .max(self.min_size_floor.h);
         let max_width = max_size.map(|s| s.w).unwrap_or(i32::max_value());
         let max_height = max_size.map(|s| s.h).unwrap_or(i32::max_value());
 
+        if let Some(ratio) = self.aspect_ratio {
+            // nearest width within the limits of both dimensions, which keeps the ratio
+            let lower = min_width.max((min_height as f64 * ratio).ceil() as i32);
+            let upper = if max_height == i32::max_value() {
+                max_width
+            } else {
+                max_width.min((max_height as f64 * ratio).floor() as i32)
+            };
+            if lower <= upper {
+                new_window_width = new_window_width.clamp(lower, upper);
+                new_window_height = (new_window_width as f64 / ratio).round() as i32;
+            }
+        }
         new_window_width = new_window_width.max(min_width).min(max_width);
         new_window_height = new_window_height.max(min_height).min(max_height);
 
         self.last_window_size = (new_window_width, new_window_height).into();
+        if self.window.is_window() {
+            self.last_window_size = self
+                .window
+                .active_window()
+                .constrain_aspect(self.last_window_size);
+        }
 
         self.window.set_resizing(true);
-        self.window.set_geometry(Rectangle::from_loc_and_size(
+        self.window.configure_geometry(Rectangle::from_loc_and_size(
             match self.window.active_window() {
                 CosmicSurface::X11(s) => s.geometry().loc,
                 _ => (0, 0).into(),
             },
             self.last_window_size,
         ));
-        self.window.configure();
     }
 
     fn relative_motion(
@@ -135,20 +177,23 @@ impl PointerGrab<State> for ResizeSurfaceGrab {
             }
 
             self.window.set_resizing(false);
-            self.window.set_geometry(Rectangle::from_loc_and_size(
+            self.window.configure_geometry(Rectangle::from_loc_and_size(
                 match self.window.active_window() {
                     CosmicSurface::X11(s) => s.geometry().loc,
                     _ => (0, 0).into(),
                 },
                 self.last_window_size,
             ));
-            self.window.configure();
 
             let mut resize_state = self.window.resize_state.lock().unwrap();
             if let Some(ResizeState::Resizing(resize_data)) = *resize_state {
                 *resize_state = Some(ResizeState::WaitingForCommit(resize_data));
             } else {
-                panic!("invalid resize state: {:?}", resize_state);
+                // the window was unmapped or remapped during the grab
+                warn!(
+                    ?resize_state,
+                    "Resize grab ended with unexpected resize state."
+                );
             }
         }
     }
@@ -174,6 +219,8 @@ impl ResizeSurfaceGrab {
         edges: ResizeEdge,
         initial_window_location: Point<i32, Logical>,
         initial_window_size: Size<i32, Logical>,
+        min_size_floor: Size<i32, Logical>,
+        aspect_ratio: Option<f64>,
     ) -> ResizeSurfaceGrab {
         let resize_state = ResizeState::Resizing(ResizeData {
             edges,
@@ -189,6 +236,8 @@ impl ResizeSurfaceGrab {
             edges,
             initial_window_size,
             last_window_size: initial_window_size,
+            min_size_floor,
+            aspect_ratio,
         }
     }
 
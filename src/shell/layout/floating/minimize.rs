@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use smithay::{
+    output::Output,
+    utils::{IsAlive, Logical, Point, Rectangle},
+};
+
+use crate::shell::{element::CosmicMapped, CosmicSurface};
+
+use super::FloatingLayout;
+
+/// A window taken out of the layout by minimizing it
+#[derive(Debug)]
+pub(super) struct MinimizedWindow {
+    window: CosmicMapped,
+    output: Output,
+    /// Output-local geometry before minimizing
+    geometry: Rectangle<i32, Logical>,
+    /// Position in the stacking order before minimizing, bottom-most first
+    stack_index: usize,
+    /// Whether the window was maximized, to be maximized again once restored
+    maximized: bool,
+}
+
+impl FloatingLayout {
+    /// Minimizes every visible window on `output`, uncovering the desktop.
+    pub fn minimize_all(&mut self, output: &Output) {
+        let Some(output_loc) = self.space.output_geometry(output).map(|geo| geo.loc) else {
+            return;
+        };
+        let windows = self
+            .space
+            .elements()
+            .enumerate()
+            .filter(|(_, elem)| {
+                !elem.is_hidden()
+                    && self.most_overlapped_output_for_element(elem).as_ref() == Some(output)
+            })
+            .map(|(idx, elem)| (idx, elem.clone()))
+            .collect::<Vec<_>>();
+        if windows.is_empty() {
+            return;
+        }
+
+        for (stack_index, window) in windows {
+            self.take_minimized(window, output, output_loc, stack_index);
+        }
+        self.minimized_all.insert(output.clone());
+    }
+
+    /// Takes `mapped` out of the layout, remembering its geometry and stacking position.
+    pub fn minimize(&mut self, mapped: &CosmicMapped) -> bool {
+        let Some(stack_index) = self.space.elements().position(|e| e == mapped) else {
+            return false;
+        };
+        let Some(output) = self.most_overlapped_output_for_element(mapped) else {
+            return false;
+        };
+        let output_loc = self.space.output_geometry(&output).unwrap().loc;
+        self.take_minimized(mapped.clone(), &output, output_loc, stack_index);
+        true
+    }
+
+    fn take_minimized(
+        &mut self,
+        window: CosmicMapped,
+        output: &Output,
+        output_loc: Point<i32, Logical>,
+        stack_index: usize,
+    ) {
+        let mut geometry = self.space.element_geometry(&window).unwrap();
+        geometry.loc -= output_loc;
+        self.space.unmap_elem(&window);
+        if self.is_raise_deferred(&window) {
+            self.deferred_raise = None;
+        }
+        for (surface, _) in window.windows() {
+            surface.set_minimized(true);
+        }
+        self.minimized.push(MinimizedWindow {
+            maximized: window.is_maximized(true),
+            window,
+            output: output.clone(),
+            geometry,
+            stack_index,
+        });
+    }
+
+    /// Brings back the minimized `mapped` at its previous geometry, on top of all other windows.
+    ///
+    /// Returns the output it is restored on and whether it was maximized when minimized,
+    /// or `None` if it isn't minimized.
+    pub fn restore(&mut self, mapped: &CosmicMapped) -> Option<(Output, bool)> {
+        let idx = self
+            .minimized
+            .iter()
+            .position(|minimized| &minimized.window == mapped)?;
+        let minimized = self.minimized.remove(idx);
+        for (surface, _) in minimized.window.windows() {
+            surface.set_minimized(false);
+        }
+        self.minimized_all.remove(&minimized.output);
+
+        let output = if self.space.outputs().any(|o| o == &minimized.output) {
+            self.place(minimized.window, &minimized.output, minimized.geometry);
+            minimized.output
+        } else {
+            let output = self.space.outputs().next()?.clone();
+            self.map_internal(minimized.window, &output, None);
+            output
+        };
+        Some((output, minimized.maximized))
+    }
+
+    /// Minimized element containing `surface`
+    pub fn minimized_element(&self, surface: &CosmicSurface) -> Option<&CosmicMapped> {
+        self.minimized
+            .iter()
+            .map(|minimized| &minimized.window)
+            .find(|mapped| mapped.windows().any(|(w, _)| &w == surface))
+    }
+
+    /// Brings back all windows minimized on `output` at their previous geometry and stacking order.
+    ///
+    /// Restored windows are put on top of windows mapped in the meantime.
+    pub fn restore_all_minimized(&mut self, output: &Output) {
+        self.minimized_all.remove(output);
+        let (mut restore, minimized): (Vec<_>, Vec<_>) = std::mem::take(&mut self.minimized)
+            .into_iter()
+            .partition(|minimized| &minimized.output == output);
+        self.minimized = minimized;
+
+        restore.sort_by_key(|minimized| minimized.stack_index);
+        for minimized in restore.into_iter().filter(|m| m.window.alive()) {
+            for (surface, _) in minimized.window.windows() {
+                surface.set_minimized(false);
+            }
+            self.place(minimized.window, output, minimized.geometry);
+        }
+    }
+
+    /// Restores the windows of `output` if the last action was [`FloatingLayout::minimize_all`],
+    /// otherwise minimizes them. Returns whether the windows are minimized now.
+    pub fn toggle_minimize_all(&mut self, output: &Output) -> bool {
+        if self.minimized_all.contains(output) {
+            self.restore_all_minimized(output);
+            false
+        } else {
+            self.minimize_all(output);
+            self.minimized_all.contains(output)
+        }
+    }
+
+    pub fn is_minimized(&self, window: &CosmicMapped) -> bool {
+        self.minimized
+            .iter()
+            .any(|minimized| &minimized.window == window)
+    }
+
+    /// Like [`FloatingLayout::windows`], followed by the minimized windows if `include_minimized` is set.
+    pub fn windows_with_minimized(
+        &self,
+        include_minimized: bool,
+    ) -> impl Iterator<Item = CosmicSurface> + '_ {
+        self.windows().chain(
+            self.minimized
+                .iter()
+                .filter(move |_| include_minimized)
+                .flat_map(|minimized| minimized.window.windows().map(|(w, _)| w)),
+        )
+    }
+
+    /// Drops closed minimized windows and brings back the ones whose output was removed.
+    pub(super) fn refresh_minimized(&mut self) {
+        self.minimized.retain(|minimized| minimized.window.alive());
+        self.minimized_all
+            .retain(|output| self.space.outputs().any(|o| o == output));
+        if let Some(output) = self.space.outputs().next().cloned() {
+            // windows minimized on a removed output have nowhere to be restored to
+            let (orphaned, minimized): (Vec<_>, Vec<_>) = std::mem::take(&mut self.minimized)
+                .into_iter()
+                .partition(|minimized| !self.space.outputs().any(|o| o == &minimized.output));
+            self.minimized = minimized;
+            for minimized in orphaned {
+                for (surface, _) in minimized.window.windows() {
+                    surface.set_minimized(false);
+                }
+                *minimized.window.last_geometry.lock().unwrap() = None;
+                self.map_internal(minimized.window, &output, None);
+            }
+        }
+    }
+}
@@ -1,26 +1,41 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
+use keyframe::{ease, functions::EaseOutCubic};
+use serde::Deserialize;
 use smithay::{
     backend::renderer::{
-        element::{AsRenderElements, RenderElement},
+        element::{utils::RescaleRenderElement, AsRenderElements, Id, RenderElement},
         ImportAll, ImportMem, Renderer,
     },
     desktop::{layer_map_for_output, space::SpaceElement, Space, WindowSurfaceType},
     input::{pointer::GrabStartData as PointerGrabStartData, Seat},
     output::Output,
-    utils::{Logical, Point, Rectangle, Size},
+    utils::{IsAlive, Logical, Point, Rectangle, Scale, Size},
 };
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+#[cfg(feature = "debug")]
+use tracing::debug;
+use tracing::warn;
 
 use crate::{
-    backend::render::{element::AsGlowRenderer, IndicatorShader},
+    backend::render::{
+        background_luminance, element::AsGlowRenderer, BackdropShader, IndicatorShader, Key,
+        ShadowShader, FOCUS_INDICATOR_COLOR, FOCUS_INDICATOR_COLOR_DARK,
+    },
+    config::{ClickRaise, FloatingConfig, FocusWrap, NewWindowSize, WindowPlacement},
     shell::{
         element::{
             resize_indicator::ResizeIndicator, stack::CosmicStackRenderElement,
-            window::CosmicWindowRenderElement, CosmicMapped, CosmicMappedRenderElement,
+            surface::SSD_HEIGHT, window::CosmicWindowRenderElement, CosmicMapped,
+            CosmicMappedRenderElement,
         },
-        focus::target::KeyboardFocusTarget,
+        focus::{target::KeyboardFocusTarget, FocusStack},
         grabs::ResizeEdge,
+        layout::tiling::Direction,
         CosmicSurface, ResizeDirection, ResizeMode,
     },
     state::State,
@@ -28,21 +43,377 @@ use crate::{
     wayland::protocols::toplevel_info::ToplevelInfoState,
 };
 
+mod geometry_store;
 mod grabs;
+mod minimize;
+mod persistence;
+mod placement;
+pub use self::geometry_store::GeometryStore;
 pub use self::grabs::*;
+use self::minimize::MinimizedWindow;
+use self::persistence::RememberedFlags;
+pub use self::persistence::*;
+pub use self::placement::*;
+
+/// Order of the windows returned by [`FloatingLayout::windows_sorted_by`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SortBy {
+    /// Most recently focused first
+    Mru,
+    /// Top-most first
+    StackOrder,
+    AppId,
+    /// Grouped by output, ordered by output position
+    Output,
+}
+
+/// Share of a window's area, that another output has to overlap more than
+/// the current one, before the window is considered to have moved there.
+const OUTPUT_CHANGE_HYSTERESIS: f64 = 0.1;
+
+/// Distance the pointer may move between press and release, for it to still count as a click.
+const CLICK_DRAG_THRESHOLD: f64 = 4.0;
+
+/// How long a sampled wallpaper luminance is reused for, if the window doesn't move.
+const LUMINANCE_CACHE_TTL: Duration = Duration::from_secs(1);
+
+/// Width and height of the part of the titlebar kept on an output, when moving or resizing windows with the keyboard.
+const MIN_VISIBLE_TITLEBAR: i32 = 32;
+
+/// Size given to windows mapped without a size of their own
+const DEFAULT_WINDOW_SIZE: (i32, i32) = (640, 480);
+
+/// Number of previous geometries kept per window for [`FloatingLayout::undo_geometry`]
+const GEOMETRY_HISTORY_DEPTH: usize = 16;
+
+/// Color and alpha of the highlight of windows waiting for their close to be confirmed
+const CLOSE_PROMPT_COLOR: [f32; 3] = [0.8, 0.1, 0.1];
+const CLOSE_PROMPT_ALPHA: f32 = 0.35;
+
+/// Offset between cascaded windows
+const CASCADE_STEP: i32 = 32;
+
+/// Longest time a new window is kept hidden, while placement is held back.
+const MAX_MAP_DEFERRAL: Duration = Duration::from_millis(500);
+
+/// Changes of the floating layout, collected until [`FloatingLayout::take_events`] is called.
+#[derive(Debug, Clone)]
+pub enum FloatingEvent {
+    /// The output a window is mostly shown on changed from `previous` to `output`
+    OutputChanged {
+        window: CosmicMapped,
+        previous: Output,
+        output: Output,
+    },
+    /// The title of the focused window changed.
+    ///
+    /// Changes in between two refreshes are coalesced into a single event.
+    TitleChanged { window: CosmicMapped, title: String },
+}
+
+/// Geometries of all windows at a point in time, see [`FloatingLayout::snapshot`]
+#[cfg(feature = "debug")]
+#[derive(Debug, Clone)]
+pub struct GeometrySnapshot {
+    pub taken: Instant,
+    pub windows: Vec<(CosmicMapped, String, Rectangle<i32, Logical>)>,
+}
+
+/// Change of a single window between two [`GeometrySnapshot`]s
+#[cfg(feature = "debug")]
+#[derive(Debug, Clone)]
+pub struct GeometryDelta {
+    pub window: CosmicMapped,
+    pub app_id: String,
+    pub before: Option<Rectangle<i32, Logical>>,
+    pub after: Option<Rectangle<i32, Logical>>,
+    /// Movement (x, y) and size change (width, height), if the window is in both snapshots
+    pub delta: Option<(i32, i32, i32, i32)>,
+}
+
+#[cfg(feature = "debug")]
+impl GeometrySnapshot {
+    /// Windows which moved, resized, appeared or disappeared until `later` was taken.
+    pub fn diff(&self, later: &GeometrySnapshot) -> Vec<GeometryDelta> {
+        let before = self
+            .windows
+            .iter()
+            .map(|(window, app_id, geo)| (window, (app_id, *geo)))
+            .collect::<HashMap<_, _>>();
+        let after = later
+            .windows
+            .iter()
+            .map(|(window, app_id, geo)| (window, (app_id, *geo)))
+            .collect::<HashMap<_, _>>();
+
+        let mut deltas = Vec::new();
+        for (window, (app_id, before_geo)) in &before {
+            let after_geo = after.get(window).map(|(_, geo)| *geo);
+            if after_geo == Some(*before_geo) {
+                continue;
+            }
+            deltas.push(GeometryDelta {
+                window: (*window).clone(),
+                app_id: (*app_id).clone(),
+                before: Some(*before_geo),
+                after: after_geo,
+                delta: after_geo.map(|after_geo| {
+                    (
+                        after_geo.loc.x - before_geo.loc.x,
+                        after_geo.loc.y - before_geo.loc.y,
+                        after_geo.size.w - before_geo.size.w,
+                        after_geo.size.h - before_geo.size.h,
+                    )
+                }),
+            });
+        }
+        for (window, (app_id, after_geo)) in &after {
+            if !before.contains_key(window) {
+                deltas.push(GeometryDelta {
+                    window: (*window).clone(),
+                    app_id: (*app_id).clone(),
+                    before: None,
+                    after: Some(*after_geo),
+                    delta: None,
+                });
+            }
+        }
+        deltas
+    }
+
+    /// Logs the changes until `later` was taken.
+    pub fn log_diff(&self, later: &GeometrySnapshot) {
+        let elapsed = later.taken.saturating_duration_since(self.taken);
+        for delta in self.diff(later) {
+            debug!(
+                app_id = %delta.app_id,
+                before = ?delta.before,
+                after = ?delta.after,
+                delta = ?delta.delta,
+                ?elapsed,
+                "Window geometry changed."
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseAction {
+    /// Forward the close request to the client
+    Close,
+    /// Hold back the close request until it is confirmed
+    Defer,
+}
+
+/// Operation applied to windows by [`FloatingLayout::for_each_matching`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum WindowAction {
+    /// Closes the windows, subject to close confirmation like any other close request
+    Close,
+    Minimize,
+    SetPinned(bool),
+    SetHidden(bool),
+}
+
+/// Stacking position of a window temporarily raised by [`FloatingLayout::peek_raise`]
+#[derive(Debug)]
+pub struct PeekToken {
+    window: CosmicMapped,
+    index: usize,
+}
+
+/// Transition of a window between two geometries in space coordinates, e.g. when (un)maximizing
+#[derive(Debug)]
+struct GeometryAnimation {
+    from: Rectangle<i32, Logical>,
+    to: Rectangle<i32, Logical>,
+    /// Global geometry the window gets configured with once the animation ends
+    configure: Rectangle<i32, Logical>,
+    start: Instant,
+}
+
+/// Everything the layout keeps track of for one of its windows
+#[derive(Debug, Default)]
+struct WindowState {
+    /// App id the window was first mapped with, `None` until then
+    app_id: Option<String>,
+    /// Output the window is considered to be on, which it follows when the output moves
+    home_output: Option<Output>,
+    border_id: Option<Id>,
+    /// Window hidden by this one swallowing it, shown again once this one is closed
+    swallowed: Option<CosmicMapped>,
+    maximized_around_pinned: bool,
+    maximized_axis: bool,
+    snapped: bool,
+    geometry_animation: Option<GeometryAnimation>,
+    /// Unrounded location, if placed by the pointer, to render the window without jitter on fractional scales
+    precise_location: Option<Point<f64, Logical>>,
+}
 
 #[derive(Debug, Default)]
 pub struct FloatingLayout {
     pub(in crate::shell) space: Space<CosmicMapped>,
+    config: FloatingConfig,
+    /// Windows with a deferred close request, highlighted until the close gets confirmed
+    pending_close: Vec<(CosmicSurface, Id)>,
+    /// Windows arranged by the last [`FloatingLayout::quarter_tile`], which are restored together
+    tiled_arrangement: Vec<CosmicMapped>,
+    window_state: HashMap<CosmicMapped, WindowState>,
+    events: Vec<FloatingEvent>,
+    recently_closed: Vec<(String, Rectangle<i32, Logical>, Instant)>,
+    remembered_flags: HashMap<String, RememberedFlags>,
+    deferred_raise: Option<(CosmicMapped, Point<f64, Logical>)>,
+    /// Offset of the last cascaded window from the top-left corner of the work area, per output
+    cascade_offsets: HashMap<Output, Point<i32, Logical>>,
+    /// Background luminance sampled around windows while rendering, with the geometry and time it was sampled at
+    indicator_luminance: RefCell<HashMap<CosmicMapped, (Rectangle<i32, Logical>, Instant, f32)>>,
+    placement_hold: Option<Instant>,
+    deferred_maps: Vec<(CosmicMapped, Output, Instant)>,
+    minimized: Vec<MinimizedWindow>,
+    minimized_all: HashSet<Output>,
+    /// The focused window and its title during the last refresh
+    focused_title: Option<(CosmicMapped, String)>,
+    /// Outputs and their geometry during the last refresh, to notice hotplugging
+    output_layout: Vec<(Output, Rectangle<i32, Logical>)>,
+    /// Window raised by [`FloatingLayout::toggle_peek`], put back by the next toggle
+    peeked: Option<PeekToken>,
 }
 
 impl FloatingLayout {
-    pub fn new() -> FloatingLayout {
-        Default::default()
+    pub fn new(config: FloatingConfig) -> FloatingLayout {
+        FloatingLayout {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Size of the grid moved and resized windows are aligned to, 0 if disabled.
+    pub fn grid_size(&self) -> u32 {
+        self.config.grid_size
+    }
+
+    /// Area of the output usable for placing floating windows, excluding the outer gap.
+    ///
+    /// `exclude` is not counted as another window on the output, when applying smart gaps.
+    pub(in crate::shell) fn work_area(
+        &self,
+        output: &Output,
+        exclude: Option<&CosmicMapped>,
+    ) -> Rectangle<i32, Logical> {
+        let mut geometry = {
+            let mut layer_map = layer_map_for_output(output);
+            // the zone is only updated, when the layer map gets arranged,
+            // so it might still have the size from before the output was rotated
+            let full = Rectangle::from_loc_and_size((0, 0), output.geometry().size);
+            if !full.contains_rect(layer_map.non_exclusive_zone()) {
+                layer_map.arrange();
+            }
+            layer_map.non_exclusive_zone()
+        };
+        if geometry.size.w <= 0 || geometry.size.h <= 0 {
+            warn!(
+                output = output.name(),
+                ?geometry,
+                "Exclusive zones cover the whole output, falling back to full output geometry."
+            );
+            geometry = Rectangle::from_loc_and_size((0, 0), output.geometry().size);
+        }
+        // Safe-area insets are measured from the output edges, not from the panels.
+        // An edge already covered by an exclusive zone at least as large as its inset
+        // is left untouched, otherwise the inset wins.
+        if let Some(safe) = geometry.intersection(output.safe_area()) {
+            geometry = safe;
+        }
+
+        if self.config.smart_gaps
+            && !self
+                .space
+                .elements_for_output(output)
+                .any(|e| Some(e) != exclude)
+        {
+            return geometry;
+        }
+
+        let (top, right, bottom, left) = self.outer_gap(output);
+        let (top, right, bottom, left) = (top as i32, right as i32, bottom as i32, left as i32);
+        if geometry.size.w - left - right <= 0 || geometry.size.h - top - bottom <= 0 {
+            // gaps larger than the output, ignore them
+            return geometry;
+        }
+        geometry.loc += Point::from((left, top));
+        geometry.size.w -= left + right;
+        geometry.size.h -= top + bottom;
+        geometry
+    }
+
+    fn outer_gap(&self, output: &Output) -> (u8, u8, u8, u8) {
+        self.config
+            .outputs
+            .get(&output.name())
+            .and_then(|config| config.outer_gap)
+            .unwrap_or(self.config.outer_gap)
+    }
+
+    /// Corner radius of effects drawn on top of windows on `output`, unless overridden per window.
+    fn corner_radius(&self, output: &Output) -> u8 {
+        self.config
+            .outputs
+            .get(&output.name())
+            .and_then(|config| config.corner_radius)
+            .unwrap_or(self.config.corner_radius)
+    }
+
+    fn maximize_gap(&self, output: &Output) -> u8 {
+        self.config
+            .outputs
+            .get(&output.name())
+            .and_then(|config| config.maximize_gap)
+            .unwrap_or(self.config.maximize_gap)
+    }
+
+    /// Corner radius of `elem`, or `fallback` unless set per window.
+    ///
+    /// Square for windows filling the output or work area.
+    fn window_corner_radius(&self, elem: &CosmicMapped, fallback: u8) -> u8 {
+        if elem.is_maximized(false) || elem.is_fullscreen(false) {
+            return 0;
+        }
+        elem.corner_radius().unwrap_or(fallback)
+    }
+
+    /// Minimum size for resizing windows, applied on top of the client's own minimum size.
+    fn min_size_floor(&self) -> Size<i32, Logical> {
+        let (w, h) = self.config.min_window_size;
+        (w as i32, h as i32).into()
     }
 
     pub fn map_output(&mut self, output: &Output, location: Point<i32, Logical>) {
-        self.space.map_output(output, location)
+        let previous = self.space.output_geometry(output).map(|geo| geo.loc);
+        self.space.map_output(output, location);
+
+        let Some(delta) = previous
+            .map(|previous| location - previous)
+            .filter(|delta| self.config.follow_output_moves && *delta != Point::from((0, 0)))
+        else {
+            return;
+        };
+        // keep windows on the same physical screen, remapping all of them to not change their stacking order
+        let offset = output.geometry().loc - location;
+        for elem in self.space.elements().cloned().collect::<Vec<_>>() {
+            let mut elem_loc = self.space.element_location(&elem).unwrap();
+            if self
+                .window_state
+                .get(&elem)
+                .map_or(false, |state| state.home_output.as_ref() == Some(output))
+            {
+                elem_loc += delta;
+                elem.set_geometry(Rectangle::from_loc_and_size(
+                    elem_loc + offset,
+                    elem.geometry().size,
+                ));
+            }
+            self.space.map_element(elem, elem_loc, false);
+        }
     }
 
     pub fn unmap_output(
@@ -78,72 +449,212 @@ impl FloatingLayout {
         position: impl Into<Option<Point<i32, Logical>>>,
     ) {
         let mapped = mapped.into();
-        let output = seat.active_output();
         let position = position.into();
+        let output = position
+            .is_none()
+            .then(|| {
+                self.policy_output(&mapped).or_else(|| {
+                    self.transient_parent(&mapped)
+                        .and_then(|parent| self.most_overlapped_output_for_element(parent))
+                })
+            })
+            .flatten()
+            .unwrap_or_else(|| seat.active_output());
 
         self.map_internal(mapped, &output, position)
     }
 
+    /// Mapped window, which `mapped` is a dialog or other transient window of
+    fn transient_parent(&self, mapped: &CosmicMapped) -> Option<&CosmicMapped> {
+        let window = mapped.active_window();
+        self.space.elements().find(|parent| {
+            parent != &mapped && parent.windows().any(|(w, _)| window.is_transient_for(&w))
+        })
+    }
+
+    /// Output configured for the app of `mapped` in [`FloatingConfig::app_outputs`], if it is connected.
+    fn policy_output(&self, mapped: &CosmicMapped) -> Option<Output> {
+        let name = self
+            .config
+            .app_outputs
+            .get(&mapped.active_window().app_id())?;
+        self.space.outputs().find(|o| &o.name() == name).cloned()
+    }
+
+    /// Holds back placing new windows for `duration`, e.g. while the output layout settles.
+    pub fn hold_placement(&mut self, duration: Duration) {
+        let until = Instant::now() + duration;
+        if self.placement_hold.map_or(true, |hold| hold < until) {
+            self.placement_hold = Some(until);
+        }
+    }
+
+    /// Maps a newly created window.
+    ///
+    /// While placement is held back, the window is kept hidden and only placed by
+    /// [`FloatingLayout::refresh`] once the hold ends, but no later than [`MAX_MAP_DEFERRAL`].
+    /// Returns the time the window will be placed at in that case.
+    pub fn map_new(
+        &mut self,
+        mapped: impl Into<CosmicMapped>,
+        seat: &Seat<State>,
+    ) -> Option<Instant> {
+        let mapped = mapped.into();
+        let output = seat.active_output();
+        let now = Instant::now();
+
+        let Some(hold) = self.placement_hold.filter(|hold| *hold > now) else {
+            self.map_internal(mapped, &output, None);
+            return None;
+        };
+        let deadline = hold.min(now + MAX_MAP_DEFERRAL);
+        let location = self
+            .space
+            .output_geometry(&output)
+            .map(|geo| geo.loc)
+            .unwrap_or_default();
+        mapped.set_hidden(true);
+        self.space.map_element(mapped.clone(), location, false);
+        self.deferred_maps.push((mapped, output, deadline));
+        Some(deadline)
+    }
+
+    fn place_deferred(&mut self) {
+        let now = Instant::now();
+        let held = self.placement_hold.map_or(false, |hold| hold > now);
+        let (ready, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.deferred_maps)
+            .into_iter()
+            .filter(|(mapped, _, _)| mapped.alive())
+            .partition(|(_, _, deadline)| !held || *deadline <= now);
+        self.deferred_maps = pending;
+
+        for (mapped, output, _) in ready {
+            mapped.set_hidden(false);
+            let output = if self.space.outputs().any(|o| o == &output) {
+                output
+            } else if let Some(output) = self.space.outputs().next() {
+                output.clone()
+            } else {
+                continue;
+            };
+            self.map_internal(mapped, &output, None);
+        }
+    }
+
     pub(in crate::shell) fn map_internal(
         &mut self,
         mapped: CosmicMapped,
         output: &Output,
         position: Option<Point<i32, Logical>>,
     ) {
+        self.remember_geometry(&mapped);
         let mut win_geo = mapped.geometry();
 
-        let layers = layer_map_for_output(&output);
-        let geometry = layers.non_exclusive_zone();
+        let geometry = self.shrink_by_border(self.work_area(output, Some(&mapped)));
         mapped.set_bounds(geometry.size);
-        let last_geometry = mapped.last_geometry.lock().unwrap().clone();
+        let app_id = mapped.active_window().app_id();
+        let mut last_geometry = mapped.last_geometry.lock().unwrap().clone();
+        let known = self
+            .window_state
+            .get(&mapped)
+            .map_or(false, |state| state.app_id.is_some());
+        if last_geometry.is_none() && position.is_none() && !known {
+            // reuse the geometry of a recently closed window of the same app
+            if let Some(idx) = self
+                .recently_closed
+                .iter()
+                .rposition(|(closed_app_id, _, _)| closed_app_id == &app_id)
+            {
+                last_geometry = Some(self.recently_closed.remove(idx).1);
+            }
+            if last_geometry.is_none() && self.config.persist_geometry {
+                last_geometry = self.saved_geometry(&app_id, output);
+            }
+            self.restore_flags(&mapped, &app_id);
+        }
+        // some apps, like games, break unless they get the size they ask for
+        let size_policy = if self.config.keep_initial_size.contains(&app_id) {
+            NewWindowSize::Fraction(1.0)
+        } else {
+            self.config.new_window_size
+        };
+        if !known {
+            if let Some(opacity) = self.config.app_opacity.get(&app_id) {
+                self.set_opacity(&mapped, opacity.clamp(0.0, 1.0));
+            }
+            if self.config.no_shadow.contains(&app_id) {
+                mapped.set_shadow(Some(false));
+            }
+        }
+        self.window_state.entry(mapped.clone()).or_default().app_id = Some(app_id.clone());
 
         if let Some(size) = last_geometry.map(|g| g.size) {
             win_geo.size = size;
         }
-        {
-            let (min_size, max_size) = (
-                mapped.min_size().unwrap_or((0, 0).into()),
-                mapped.max_size().unwrap_or((0, 0).into()),
+        if win_geo.size.w <= 0 || win_geo.size.h <= 0 {
+            // some clients map before committing a buffer, which would leave them invisible and ungrabbable
+            warn!(
+                ?app_id,
+                size = ?win_geo.size,
+                "Window mapped without a size, using a default size."
             );
-            if win_geo.size.w > geometry.size.w / 3 * 2 {
-                // try a more reasonable size
-                let mut width = geometry.size.w / 3 * 2;
-                if max_size.w != 0 {
-                    // don't go larger then the max_size ...
-                    width = std::cmp::min(max_size.w, width);
-                }
-                if min_size.w != 0 {
-                    // ... but also don't go smaller than the min_size
-                    width = std::cmp::max(min_size.w, width);
-                }
-                // but no matter the supported sizes, don't be larger than our non-exclusive-zone
-                win_geo.size.w = std::cmp::min(width, geometry.size.w);
-            }
-            if win_geo.size.h > geometry.size.h / 3 * 2 {
-                // try a more reasonable size
-                let mut height = geometry.size.h / 3 * 2;
-                if max_size.h != 0 {
-                    // don't go larger then the max_size ...
-                    height = std::cmp::min(max_size.h, height);
-                }
-                if min_size.h != 0 {
-                    // ... but also don't go smaller than the min_size
-                    height = std::cmp::max(min_size.h, height);
-                }
-                // but no matter the supported sizes, don't be larger than our non-exclusive-zone
-                win_geo.size.h = std::cmp::min(height, geometry.size.h);
-            }
+            win_geo.size = DEFAULT_WINDOW_SIZE.into();
         }
+        win_geo.size = Self::reasonable_size(&mapped, win_geo.size, geometry.size, size_policy);
 
-        let position = position
-            .or_else(|| last_geometry.map(|g| g.loc))
-            .unwrap_or_else(|| {
+        let parent_geometry = self
+            .transient_parent(&mapped)
+            .and_then(|parent| self.space.element_geometry(parent));
+        let output_loc = self
+            .space
+            .output_geometry(output)
+            .map(|g| g.loc)
+            .unwrap_or_default();
+        // the hint refers to the contents, below a headerbar we might add
+        let position_hint = mapped
+            .active_window()
+            .position_hint()
+            .map(|hint| {
+                hint - output.geometry().loc + output_loc - Point::from((0, mapped.header_height()))
+            })
+            .filter(|hint| {
+                self.space
+                    .output_geometry(output)
+                    .is_some_and(|geo| geo.contains(*hint))
+            });
+        let position = match (
+            position
+                .or_else(|| last_geometry.map(|g| g.loc))
+                .or(position_hint),
+            parent_geometry,
+        ) {
+            (Some(position), _) => position,
+            // center dialogs over their parent, as far as the work area allows
+            (None, Some(parent)) => {
+                let area = Rectangle::from_loc_and_size(geometry.loc + output_loc, geometry.size);
+                let x = parent.loc.x + (parent.size.w / 2) - (win_geo.size.w / 2);
+                let y = parent.loc.y + (parent.size.h / 2) - (win_geo.size.h / 2);
                 (
-                    geometry.loc.x + (geometry.size.w / 2) - (win_geo.size.w / 2) + win_geo.loc.x,
-                    geometry.loc.y + (geometry.size.h / 2) - (win_geo.size.h / 2) + win_geo.loc.y,
+                    x.clamp(
+                        area.loc.x,
+                        (area.loc.x + area.size.w - win_geo.size.w).max(area.loc.x),
+                    ) + win_geo.loc.x,
+                    y.clamp(
+                        area.loc.y,
+                        (area.loc.y + area.size.h - win_geo.size.h).max(area.loc.y),
+                    ) + win_geo.loc.y,
                 )
                     .into()
-            });
+            }
+            (None, None) if self.config.placement == WindowPlacement::Cascade => {
+                self.cascade_position(output, geometry, win_geo)
+            }
+            (None, None) => (
+                geometry.loc.x + (geometry.size.w / 2) - (win_geo.size.w / 2) + win_geo.loc.x,
+                geometry.loc.y + (geometry.size.h / 2) - (win_geo.size.h / 2) + win_geo.loc.y,
+            )
+                .into(),
+        };
 
         mapped.set_tiled(false);
         let offset = output.geometry().loc
@@ -152,19 +663,555 @@ impl FloatingLayout {
                 .output_geometry(output)
                 .map(|g| g.loc)
                 .unwrap_or_default();
-        mapped.set_geometry(Rectangle::from_loc_and_size(
+        mapped.configure_geometry(Rectangle::from_loc_and_size(
             position + offset,
             win_geo.size,
         ));
-        mapped.configure();
-        self.space.map_element(mapped, position, false);
+        self.window_state
+            .entry(mapped.clone())
+            .or_default()
+            .border_id
+            .get_or_insert_with(Id::new);
+        self.space.map_element(mapped.clone(), position, false);
+        self.update_home_output(&mapped);
+    }
+
+    /// `size` shrunk to at most the fraction of `area` given by `policy`, as far as the size hints of `mapped` allow.
+    ///
+    /// The result is never smaller than the minimum size of `mapped`, even if that doesn't fit into `area`.
+    fn reasonable_size(
+        mapped: &CosmicMapped,
+        mut size: Size<i32, Logical>,
+        area: Size<i32, Logical>,
+        policy: NewWindowSize,
+    ) -> Size<i32, Logical> {
+        let (min_size, max_size) = (
+            mapped.min_size().unwrap_or((0, 0).into()),
+            mapped.max_size().unwrap_or((0, 0).into()),
+        );
+        let limit = |extent: i32| match policy {
+            NewWindowSize::Fraction(fraction) => {
+                Some((extent as f64 * fraction.clamp(0.1, 1.0)).round() as i32)
+            }
+            NewWindowSize::Natural => None,
+        };
+        if let Some(limit) = limit(area.w).filter(|limit| size.w > *limit) {
+            // try a more reasonable size
+            let mut width = limit;
+            if max_size.w != 0 {
+                // don't go larger then the max_size ...
+                width = std::cmp::min(max_size.w, width);
+            }
+            // but no matter the supported sizes, don't be larger than our non-exclusive-zone
+            size.w = std::cmp::min(width, area.w);
+        }
+        if let Some(limit) = limit(area.h).filter(|limit| size.h > *limit) {
+            // try a more reasonable size
+            let mut height = limit;
+            if max_size.h != 0 {
+                // don't go larger then the max_size ...
+                height = std::cmp::min(max_size.h, height);
+            }
+            // but no matter the supported sizes, don't be larger than our non-exclusive-zone
+            size.h = std::cmp::min(height, area.h);
+        }
+        // ... and never go smaller than the min_size
+        if min_size.w != 0 {
+            size.w = std::cmp::max(min_size.w, size.w);
+        }
+        if min_size.h != 0 {
+            size.h = std::cmp::max(min_size.h, size.h);
+        }
+        size
+    }
+
+    /// Position of the next cascaded window of `win_geo`'s size inside `area` of `output`.
+    fn cascade_position(
+        &mut self,
+        output: &Output,
+        area: Rectangle<i32, Logical>,
+        win_geo: Rectangle<i32, Logical>,
+    ) -> Point<i32, Logical> {
+        let mut offset = self
+            .cascade_offsets
+            .get(output)
+            .map_or(Point::from((0, 0)), |previous| {
+                *previous + Point::from((CASCADE_STEP, CASCADE_STEP))
+            });
+        if offset.x + win_geo.size.w > area.size.w || offset.y + win_geo.size.h > area.size.h {
+            offset = Point::from((0, 0));
+        }
+        self.cascade_offsets.insert(output.clone(), offset);
+        area.loc + offset + win_geo.loc
+    }
+
+    /// Area left for a window's content, if the window including its border should fill `geometry`.
+    pub(in crate::shell) fn shrink_by_border(
+        &self,
+        mut geometry: Rectangle<i32, Logical>,
+    ) -> Rectangle<i32, Logical> {
+        let width = self.config.border_width as i32;
+        if geometry.size.w <= width * 2 || geometry.size.h <= width * 2 {
+            return geometry;
+        }
+        geometry.loc += Point::from((width, width));
+        geometry.size -= Size::from((width * 2, width * 2));
+        geometry
+    }
+
+    /// Width of the border around `elem`, which keeps it clear of output edges and other windows.
+    pub(in crate::shell) fn border_width(&self, elem: &CosmicMapped) -> i32 {
+        if elem.is_borderless() {
+            0
+        } else {
+            self.config.border_width as i32
+        }
+    }
+
+    /// Holds back raising `mapped` when it was clicked at `location`, if configured to do so.
+    pub fn defer_raise(&mut self, mapped: &CosmicMapped, location: Point<f64, Logical>) {
+        self.deferred_raise = (self.config.click_raise != ClickRaise::Press
+            && self.space.elements().any(|e| e == mapped))
+        .then(|| (mapped.clone(), location));
+    }
+
+    pub fn is_raise_deferred(&self, mapped: &CosmicMapped) -> bool {
+        self.deferred_raise
+            .as_ref()
+            .map_or(false, |(deferred, _)| deferred == mapped)
+    }
+
+    /// Raises a window held back by [`FloatingLayout::defer_raise`] once the button got released at `location`.
+    pub fn finish_deferred_raise(&mut self, location: Point<f64, Logical>) {
+        let Some((mapped, pressed_at)) = self.deferred_raise.take() else {
+            return;
+        };
+        let distance = location - pressed_at;
+        let dragged = distance.x.hypot(distance.y) > CLICK_DRAG_THRESHOLD;
+        if (self.config.click_raise == ClickRaise::Release || !dragged)
+            && self.space.elements().any(|e| e == &mapped)
+        {
+            self.space.raise_element(&mapped, true);
+        }
+    }
+
+    /// Whether windows of the app `mapped` belongs to may be swallowed by their children.
+    pub fn can_swallow(&self, mapped: &CosmicMapped) -> bool {
+        !mapped.is_hidden()
+            && self
+                .window_state
+                .get(mapped)
+                .and_then(|state| state.app_id.as_ref())
+                .map_or(false, |app_id| self.config.swallow.contains(app_id))
+    }
+
+    /// Hides `parent` and maps `child` in its place, until `child` is closed.
+    pub fn swallow(&mut self, parent: &CosmicMapped, child: CosmicMapped) {
+        let (Some(location), Some(output)) = (
+            self.space.element_location(parent),
+            self.space.outputs_for_element(parent).into_iter().next(),
+        ) else {
+            return;
+        };
+
+        parent.set_hidden(true);
+        *child.last_geometry.lock().unwrap() = Some(Rectangle::from_loc_and_size(
+            location,
+            parent.geometry().size,
+        ));
+        self.window_state
+            .entry(child.clone())
+            .or_default()
+            .swallowed = Some(parent.clone());
+        self.map_internal(child, &output, None);
+    }
+
+    /// Maps `mapped` at the given output-local geometry, without adjusting its size.
+    pub(in crate::shell) fn place(
+        &mut self,
+        mapped: CosmicMapped,
+        output: &Output,
+        geometry: Rectangle<i32, Logical>,
+    ) {
+        let output_loc = self
+            .space
+            .output_geometry(output)
+            .map(|g| g.loc)
+            .unwrap_or_default();
+
+        mapped.set_tiled(false);
+        mapped.configure_geometry(Rectangle::from_loc_and_size(
+            output.geometry().loc + geometry.loc,
+            geometry.size,
+        ));
+        self.window_state
+            .entry(mapped.clone())
+            .or_default()
+            .border_id
+            .get_or_insert_with(Id::new);
+        self.space
+            .map_element(mapped.clone(), output_loc + geometry.loc, false);
+        self.update_home_output(&mapped);
+    }
+
+    /// Remembers the unrounded location of `mapped` in space coordinates, after it was mapped at its rounded location.
+    pub(in crate::shell) fn set_precise_location(
+        &mut self,
+        mapped: &CosmicMapped,
+        location: Point<f64, Logical>,
+    ) {
+        if self.space.element_location(mapped) == Some(location.to_i32_round()) {
+            self.window_state
+                .entry(mapped.clone())
+                .or_default()
+                .precise_location = Some(location);
+        }
+    }
+
+    /// Unrounded location of `mapped`, unless it was moved since it got recorded.
+    fn precise_location(&self, mapped: &CosmicMapped) -> Option<Point<f64, Logical>> {
+        self.window_state
+            .get(mapped)
+            .and_then(|state| state.precise_location)
+            .filter(|location| self.space.element_location(mapped) == Some(location.to_i32_round()))
+    }
+
+    /// Like [`FloatingLayout::place`], but transitions to `geometry` over the configured maximize animation duration.
+    ///
+    /// The client is only configured with the new geometry once the animation ends.
+    /// Returns `false` without doing anything, if animations are disabled or `mapped` isn't mapped yet.
+    fn place_animated(
+        &mut self,
+        mapped: &CosmicMapped,
+        output: &Output,
+        geometry: Rectangle<i32, Logical>,
+    ) -> bool {
+        if self.config.maximize_animation_ms == 0 || mapped.animations_disabled() {
+            return false;
+        }
+        let (Some(location), Some(output_loc)) = (
+            self.space.element_location(mapped),
+            self.space.output_geometry(output).map(|g| g.loc),
+        ) else {
+            return false;
+        };
+
+        let from = Rectangle::from_loc_and_size(location, mapped.geometry().size);
+        let to = Rectangle::from_loc_and_size(output_loc + geometry.loc, geometry.size);
+        mapped.set_tiled(false);
+        self.window_state
+            .entry(mapped.clone())
+            .or_default()
+            .border_id
+            .get_or_insert_with(Id::new);
+        self.space.map_element(mapped.clone(), to.loc, false);
+        self.update_home_output(mapped);
+        self.window_state
+            .entry(mapped.clone())
+            .or_default()
+            .geometry_animation = Some(GeometryAnimation {
+            from,
+            to,
+            configure: Rectangle::from_loc_and_size(
+                output.geometry().loc + geometry.loc,
+                geometry.size,
+            ),
+            start: Instant::now(),
+        });
+        true
+    }
+
+    /// Current geometry of `mapped` in space coordinates, while it is animated between two geometries.
+    fn animated_geometry(&self, mapped: &CosmicMapped) -> Option<Rectangle<i32, Logical>> {
+        let animation = self.window_state.get(mapped)?.geometry_animation.as_ref()?;
+        let duration = Duration::from_millis(self.config.maximize_animation_ms);
+        let progress = (animation.start.elapsed().as_secs_f64() / duration.as_secs_f64()).min(1.0);
+        let t = ease(EaseOutCubic, 0.0, 1.0, progress);
+        let lerp = |from: i32, to: i32| from + ((to - from) as f64 * t).round() as i32;
+        Some(Rectangle::from_loc_and_size(
+            (
+                lerp(animation.from.loc.x, animation.to.loc.x),
+                lerp(animation.from.loc.y, animation.to.loc.y),
+            ),
+            (
+                lerp(animation.from.size.w, animation.to.size.w),
+                lerp(animation.from.size.h, animation.to.size.h),
+            ),
+        ))
+    }
+
+    pub fn animations_going(&self) -> bool {
+        self.window_state
+            .values()
+            .any(|state| state.geometry_animation.is_some())
+    }
+
+    /// Whether `window` is still transitioning to a new geometry.
+    pub fn is_animating(&self, window: &CosmicSurface) -> bool {
+        self.window_state.iter().any(|(m, state)| {
+            state.geometry_animation.is_some() && m.windows().any(|(w, _)| &w == window)
+        })
+    }
+
+    /// Transitions `window` to the maximized geometry of `output`.
+    ///
+    /// Returns `false` without doing anything, if the window can't be animated,
+    /// in which case it should be maximized right away.
+    pub fn maximize_animated(&mut self, window: &CosmicSurface, output: &Output) -> bool {
+        let Some(mapped) = self
+            .space
+            .elements()
+            .find(|m| m.windows().any(|(w, _)| &w == window))
+            .cloned()
+        else {
+            return false;
+        };
+        let geometry = self.maximized_geometry(output);
+        self.place_animated(&mapped, output, geometry)
+    }
+
+    /// Finishes elapsed animations, configuring the windows with their final geometry.
+    pub fn update_animations(&mut self) {
+        let duration = Duration::from_millis(self.config.maximize_animation_ms);
+        for (mapped, state) in self.window_state.iter_mut() {
+            if state
+                .geometry_animation
+                .as_ref()
+                .map_or(false, |animation| animation.start.elapsed() >= duration)
+            {
+                let animation = state.geometry_animation.take().unwrap();
+                mapped.configure_geometry(animation.configure);
+            }
+        }
+    }
+
+    /// Moves and resizes `window` to the output-local `geometry` on `output`, keeping its place in the stacking order.
+    ///
+    /// Returns `false` if `window` or `output` isn't part of this layout.
+    pub fn set_window_geometry(
+        &mut self,
+        window: &CosmicMapped,
+        output: &Output,
+        geometry: Rectangle<i32, Logical>,
+    ) -> bool {
+        if self.space.output_geometry(output).is_none() {
+            return false;
+        }
+        let order = self.space.elements().cloned().collect::<Vec<_>>();
+        let Some(index) = order.iter().position(|e| e == window) else {
+            return false;
+        };
+        self.place(window.clone(), output, geometry);
+        for elem in &order[index + 1..] {
+            self.space.raise_element(elem, false);
+        }
+        true
+    }
+
+    fn update_home_output(&mut self, elem: &CosmicMapped) {
+        let Some(elem_geo) = self.space.element_geometry(elem) else {
+            return;
+        };
+        let Some(output) = self.most_overlapped_output_for_element(elem) else {
+            return;
+        };
+        let overlap = |output: &Output| {
+            self.space
+                .output_geometry(output)
+                .and_then(|geo| geo.intersection(elem_geo))
+                .map_or(0, |overlap| overlap.size.w * overlap.size.h)
+        };
+
+        let current = self
+            .window_state
+            .get(elem)
+            .and_then(|state| state.home_output.as_ref());
+        let changed = match current {
+            None => None,
+            Some(current) if current == &output => return,
+            Some(current) if self.space.outputs().any(|o| o == current) => {
+                let threshold =
+                    (elem_geo.size.w * elem_geo.size.h) as f64 * OUTPUT_CHANGE_HYSTERESIS;
+                ((overlap(&output) - overlap(current)) as f64 > threshold).then(|| current.clone())
+            }
+            Some(current) => Some(current.clone()),
+        };
+
+        if let Some(previous) = changed {
+            self.events.push(FloatingEvent::OutputChanged {
+                window: elem.clone(),
+                previous,
+                output: output.clone(),
+            });
+        } else if current.is_some() {
+            return;
+        }
+        self.window_state
+            .entry(elem.clone())
+            .or_default()
+            .home_output = Some(output);
+    }
+
+    /// Returns all events, that happened since the last call
+    pub fn take_events(&mut self) -> Vec<FloatingEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn element_for_focus(&self, focused: &KeyboardFocusTarget) -> Option<&CosmicMapped> {
+        let toplevel = focused.toplevel()?;
+        self.space
+            .elements()
+            .find(|m| m.has_surface(&toplevel, WindowSurfaceType::TOPLEVEL))
+    }
+
+    /// Records the geometry of `mapped` before it gets changed, for [`FloatingLayout::undo_geometry`].
+    ///
+    /// Windows outside of the space record the geometry they were unmapped with.
+    fn remember_geometry(&self, mapped: &CosmicMapped) {
+        let Some(geometry) = self
+            .space
+            .element_geometry(mapped)
+            .or_else(|| mapped.last_geometry.lock().unwrap().clone())
+        else {
+            return;
+        };
+        let mut history = mapped.geometry_history.lock().unwrap();
+        if history.back() != Some(&geometry) {
+            if history.len() >= GEOMETRY_HISTORY_DEPTH {
+                history.pop_front();
+            }
+            history.push_back(geometry);
+        }
+    }
+
+    /// Restores the geometry `mapped` had before it was last moved, resized, placed or maximized.
+    ///
+    /// Maximized windows aren't handled here, they are restored by unmaximizing them.
+    pub fn undo_geometry(&mut self, mapped: &CosmicMapped) -> bool {
+        if mapped.is_maximized(true) || mapped.is_fullscreen(true) {
+            return false;
+        }
+        let Some(current) = self.space.element_geometry(mapped) else {
+            return false;
+        };
+        let previous = {
+            let mut history = mapped.geometry_history.lock().unwrap();
+            // operations that didn't change anything, like a resize grab without motion, are skipped
+            std::iter::from_fn(|| history.pop_back()).find(|geo| *geo != current)
+        };
+        let Some(previous) = previous else {
+            return false;
+        };
+        let Some(output) = self
+            .space
+            .output_under(previous.loc.to_f64())
+            .next()
+            .cloned()
+            .or_else(|| self.most_overlapped_output_for_element(mapped))
+        else {
+            return false;
+        };
+        let Some(output_geo) = self.space.output_geometry(&output) else {
+            return false;
+        };
+        self.unsnap(mapped);
+        self.set_window_geometry(
+            mapped,
+            &output,
+            Rectangle::from_loc_and_size(previous.loc - output_geo.loc, previous.size),
+        )
+    }
+
+    /// Restores the geometry the focused window had before it was last placed, see [`FloatingLayout::undo_geometry`].
+    ///
+    /// If the window was arranged by [`FloatingLayout::quarter_tile`], all windows of that arrangement are restored.
+    pub fn undo_placement(&mut self, focused: &KeyboardFocusTarget) -> bool {
+        let Some(mapped) = self.element_for_focus(focused).cloned() else {
+            return false;
+        };
+        let windows = if self.tiled_arrangement.contains(&mapped) {
+            std::mem::take(&mut self.tiled_arrangement)
+        } else {
+            vec![mapped]
+        };
+
+        let mut restored = false;
+        for mapped in windows {
+            restored |= self.undo_geometry(&mapped);
+        }
+        restored
+    }
+
+    /// Raises `window` above all others, without losing its previous stacking position.
+    pub fn peek_raise(&mut self, window: &CosmicMapped) -> Option<PeekToken> {
+        let index = self.space.elements().position(|e| e == window)?;
+        self.space.raise_element(window, false);
+        Some(PeekToken {
+            window: window.clone(),
+            index,
+        })
+    }
+
+    /// Puts a window raised by [`FloatingLayout::peek_raise`] back to where it was stacked.
+    ///
+    /// Does nothing, if the window got unmapped in the meantime.
+    pub fn restore_peek(&mut self, token: PeekToken) {
+        let mut order = self.space.elements().cloned().collect::<Vec<_>>();
+        let Some(current) = order.iter().position(|e| e == &token.window) else {
+            return;
+        };
+        order.remove(current);
+        let index = token.index.min(order.len());
+        order.insert(index, token.window);
+        for elem in &order[index..] {
+            self.space.raise_element(elem, false);
+        }
+    }
+
+    /// Raises the window at `location` above all others, or puts the window raised by the previous call back.
+    pub fn toggle_peek(&mut self, location: Point<f64, Logical>) {
+        if let Some(token) = self.peeked.take() {
+            self.restore_peek(token);
+        } else if let Some(window) = self.element_under(location).map(|(w, _)| w.clone()) {
+            self.peeked = self.peek_raise(&window);
+        }
+    }
+
+    /// Moves `mapped` right below `above` in the stacking order.
+    pub fn stack_below(&mut self, mapped: &CosmicMapped, above: &CosmicMapped) -> bool {
+        let mut order = self.space.elements().cloned().collect::<Vec<_>>();
+        let Some(current) = order.iter().position(|e| e == mapped) else {
+            return false;
+        };
+        order.remove(current);
+        let Some(index) = order.iter().position(|e| e == above) else {
+            return false;
+        };
+        order.insert(index, mapped.clone());
+        for elem in &order[index..] {
+            self.space.raise_element(elem, false);
+        }
+        true
     }
 
     pub fn unmap(&mut self, window: &CosmicMapped) -> bool {
         #[allow(irrefutable_let_patterns)]
         let is_maximized = window.is_maximized(true);
+        let was_deferred = self
+            .deferred_maps
+            .iter()
+            .position(|(mapped, _, _)| mapped == window)
+            .map(|idx| self.deferred_maps.remove(idx))
+            .is_some();
+        if was_deferred {
+            window.set_hidden(false);
+        }
 
-        if !is_maximized {
+        // keep the geometry from before snapping, to restore it on the next map
+        let was_snapped = self.window_state.get_mut(window).map_or(false, |state| {
+            std::mem::take(&mut state.snapped) | std::mem::take(&mut state.maximized_axis)
+        });
+        if !is_maximized && !was_deferred && !was_snapped {
             if let Some(location) = self.space.element_location(window) {
                 *window.last_geometry.lock().unwrap() = Some(Rectangle::from_loc_and_size(
                     location,
@@ -182,17 +1229,260 @@ impl FloatingLayout {
         self.space.element_geometry(elem)
     }
 
+    /// Marks `mapped` as always-on-top, drawing it and taking input for it above all other windows.
+    pub fn set_always_on_top(&mut self, mapped: &CosmicMapped, value: bool) {
+        mapped.set_always_on_top(value);
+        if value && self.space.elements().any(|e| e == mapped) {
+            self.space.raise_element(mapped, false);
+        }
+    }
+
+    /// Marks `mapped` as sticky, moving it along to every workspace that gets activated.
+    ///
+    /// Only windows of this layout can be sticky.
+    pub fn set_sticky(&mut self, mapped: &CosmicMapped, value: bool) {
+        if value && !self.space.elements().any(|e| e == mapped) {
+            return;
+        }
+        mapped.set_sticky(value);
+    }
+
+    /// Whether `mapped` is a window of this layout, that follows the active workspace.
+    pub fn is_sticky(&self, mapped: &CosmicMapped) -> bool {
+        mapped.is_sticky() && self.space.elements().any(|e| e == mapped)
+    }
+
+    /// Toggles the server-side headerbar and border of `mapped`.
+    ///
+    /// The content is kept in place by [`FloatingLayout::decoration_acked`], once the client acked the change.
+    /// Returns `false` if `mapped` isn't a window of this layout, or its decorations can't be switched.
+    pub fn set_decorated(&mut self, mapped: &CosmicMapped, decorated: bool) -> bool {
+        if !mapped.is_window() || self.space.element_geometry(mapped).is_none() {
+            return false;
+        }
+        if !mapped.active_window().set_server_decorated(decorated) {
+            return false;
+        }
+        mapped.set_borderless(!decorated);
+        mapped.active_window().send_configure();
+        true
+    }
+
+    /// Shifts `mapped` after its client acked switching server-side decorations to `decorated`,
+    /// so that its content stays in place.
+    ///
+    /// Returns `false` if `mapped` isn't a window of this layout.
+    pub fn decoration_acked(&mut self, mapped: &CosmicMapped, decorated: bool) -> bool {
+        if !mapped.is_window() {
+            return false;
+        }
+        let Some(geometry) = self.space.element_geometry(mapped) else {
+            return false;
+        };
+        let previous_header = mapped.header_height();
+        let header = if decorated { SSD_HEIGHT } else { 0 };
+        let shift = |geo: Rectangle<i32, Logical>| {
+            Rectangle::from_loc_and_size(
+                geo.loc - Point::from((0, header - previous_header)),
+                geo.size + Size::from((0, header - previous_header)),
+            )
+        };
+
+        if mapped.is_maximized(true) || mapped.is_fullscreen(true) {
+            // the maximized size doesn't change, only the geometry to restore to does
+            let mut last_geometry = mapped.last_geometry.lock().unwrap();
+            *last_geometry = last_geometry.map(shift);
+            drop(last_geometry);
+            mapped.configure_geometry(geometry);
+            return true;
+        }
+
+        let geometry = shift(geometry);
+        *mapped.last_geometry.lock().unwrap() = Some(geometry);
+        let Some(output) = self.most_overlapped_output_for_element(mapped) else {
+            return false;
+        };
+        let Some(output_geo) = self.space.output_geometry(&output) else {
+            return false;
+        };
+        self.set_window_geometry(
+            mapped,
+            &output,
+            Rectangle::from_loc_and_size(geometry.loc - output_geo.loc, geometry.size),
+        )
+    }
+
+    /// Unmaps all sticky windows, returning them with their output and output-local geometry.
+    pub(in crate::shell) fn take_sticky(
+        &mut self,
+    ) -> Vec<(CosmicMapped, Output, Rectangle<i32, Logical>)> {
+        let sticky = self
+            .space
+            .elements()
+            .filter(|e| e.is_sticky())
+            .cloned()
+            .collect::<Vec<_>>();
+        sticky
+            .into_iter()
+            .filter_map(|mapped| {
+                let output = self.most_overlapped_output_for_element(&mapped)?;
+                let mut geometry = self.space.element_geometry(&mapped)?;
+                geometry.loc -= self.space.output_geometry(&output)?.loc;
+                self.unmap(&mapped);
+                Some((mapped, output, geometry))
+            })
+            .collect()
+    }
+
+    /// Sets the opacity the contents of `mapped` are rendered with, decorations like the focus indicator stay opaque.
+    pub fn set_opacity(&mut self, mapped: &CosmicMapped, opacity: f32) {
+        mapped.set_opacity(opacity);
+    }
+
+    /// `elements` from top to bottom, with always-on-top windows above all others.
+    ///
+    /// Both groups keep their stacking order, which follows focus recency, as focusing raises windows.
+    fn top_down<'a>(
+        &self,
+        elements: impl DoubleEndedIterator<Item = &'a CosmicMapped>,
+    ) -> Vec<&'a CosmicMapped> {
+        let (mut on_top, rest): (Vec<_>, Vec<_>) =
+            elements.rev().partition(|elem| elem.is_always_on_top());
+        on_top.extend(rest);
+        on_top
+    }
+
+    pub fn element_under(
+        &self,
+        location: Point<f64, Logical>,
+    ) -> Option<(&CosmicMapped, Point<i32, Logical>)> {
+        self.top_down(self.space.elements())
+            .into_iter()
+            .filter(|elem| !elem.is_hidden())
+            .find_map(|elem| {
+                let render_location =
+                    self.space.element_location(elem).unwrap() - elem.geometry().loc;
+                let mut bbox = elem.bbox();
+                bbox.loc += render_location;
+                (bbox.to_f64().contains(location)
+                    && elem.is_in_input_region(&(location - render_location.to_f64())))
+                .then_some((elem, render_location))
+            })
+    }
+
+    /// Window whose titlebar or tab bar is at `location`
+    pub fn header_under(&self, location: Point<f64, Logical>) -> Option<&CosmicMapped> {
+        let (elem, _) = self.element_under(location)?;
+        let geometry = self.space.element_geometry(elem)?;
+        (location.y < (geometry.loc.y + elem.header_height()) as f64).then_some(elem)
+    }
+
+    /// Merges `dragged` into `target`, turning it into a stack if it isn't one already.
+    ///
+    /// The stack keeps the geometry of `target`, constrained to the size limits shared by all its windows,
+    /// and `dragged` leaves the space. Returns the resulting stack.
+    pub fn stack_with(
+        &mut self,
+        target: &CosmicMapped,
+        dragged: &CosmicMapped,
+    ) -> Option<CosmicMapped> {
+        if target == dragged {
+            return None;
+        }
+        let output = self.most_overlapped_output_for_element(target)?;
+        let output_loc = self.space.output_geometry(&output)?.loc;
+        let mut geometry = self.space.element_geometry(target)?;
+        geometry.loc -= output_loc;
+
+        self.unmap(dragged);
+        self.unmap(target);
+
+        let mut stack = target.clone();
+        stack.convert_to_stack(std::iter::once((&output, stack.bbox())));
+        for (surface, _) in dragged.windows() {
+            stack.stack_ref().unwrap().add_window(surface, None);
+        }
+        // both windows are only known as part of the stack from now on
+        self.transfer_state(target, &stack);
+        self.forget_state(target);
+        self.forget_state(dragged);
+
+        // windows might not agree on their limits, the minimum wins in that case
+        let min = stack.min_size().unwrap_or((1, 1).into());
+        let max = stack.max_size().unwrap_or_default();
+        let clamp = |value: i32, min: i32, max: i32| {
+            let value = if max > 0 { value.min(max) } else { value };
+            value.max(min)
+        };
+        geometry.size = (
+            clamp(geometry.size.w, min.w, max.w),
+            clamp(geometry.size.h, min.h, max.h),
+        )
+            .into();
+
+        self.place(stack.clone(), &output, geometry);
+        Some(stack)
+    }
+
+    /// Moves the per-window state of `from` over to `to`, which replaced it in the layout.
+    fn transfer_state(&mut self, from: &CosmicMapped, to: &CosmicMapped) {
+        if let Some(from_state) = self.window_state.remove(from) {
+            let to_state = self.window_state.entry(to.clone()).or_default();
+            to_state.app_id = from_state.app_id;
+            to_state.border_id = from_state.border_id;
+            to_state.home_output = from_state.home_output;
+        }
+        if let Some((focused, _)) = self.focused_title.as_mut().filter(|(w, _)| w == from) {
+            *focused = to.clone();
+        }
+    }
+
+    fn is_snapped(&self, mapped: &CosmicMapped) -> bool {
+        self.window_state
+            .get(mapped)
+            .map_or(false, |state| state.snapped)
+    }
+
+    /// Forgets that `mapped` is snapped, returning whether it was.
+    fn unsnap(&mut self, mapped: &CosmicMapped) -> bool {
+        self.window_state
+            .get_mut(mapped)
+            .map_or(false, |state| std::mem::take(&mut state.snapped))
+    }
+
+    /// Drops all per-window state of `mapped`, which isn't part of the layout anymore.
+    fn forget_state(&mut self, mapped: &CosmicMapped) {
+        self.window_state.remove(mapped);
+        if self
+            .focused_title
+            .as_ref()
+            .map_or(false, |(w, _)| w == mapped)
+        {
+            self.focused_title = None;
+        }
+        self.tiled_arrangement.retain(|w| w != mapped);
+        self.indicator_luminance.borrow_mut().remove(mapped);
+    }
+
     pub fn maximize_request(&mut self, window: &CosmicSurface) {
+        let maximized = window.is_maximized(true);
         if let Some(mapped) = self
             .space
             .elements()
             .find(|m| m.windows().any(|(w, _)| &w == window))
+            .cloned()
         {
-            if let Some(location) = self.space.element_location(mapped) {
-                *mapped.last_geometry.lock().unwrap() = Some(Rectangle::from_loc_and_size(
-                    location,
-                    mapped.geometry().size,
-                ));
+            if !maximized {
+                self.remember_geometry(&mapped);
+                self.unsnap(&mapped);
+            }
+            if let Some(location) = self.space.element_location(&mapped) {
+                let mut last_geometry = mapped.last_geometry.lock().unwrap();
+                *last_geometry = stash_for_maximize(
+                    *last_geometry,
+                    Rectangle::from_loc_and_size(location, mapped.geometry().size),
+                    maximized,
+                );
             }
         }
     }
@@ -204,32 +1494,126 @@ impl FloatingLayout {
             .find(|m| m.windows().any(|(w, _)| &w == window))
             .cloned();
 
-        if let Some(mapped) = maybe_mapped {
-            let last_geometry = mapped.last_geometry.lock().unwrap().clone();
-            let last_size = last_geometry.map(|g| g.size).expect("No previous size?");
-            let last_location = last_geometry.map(|g| g.loc).expect("No previous location?");
-            let output = self
-                .space
-                .output_under(last_location.to_f64())
-                .next()
-                .unwrap_or(self.space.outputs().next().unwrap());
-            let offset = output.geometry().loc
-                - self
-                    .space
-                    .output_geometry(output)
-                    .map(|g| g.loc)
-                    .unwrap_or_default();
-            mapped.set_geometry(Rectangle::from_loc_and_size(
-                last_location + offset,
-                last_size,
-            ));
-            self.space.map_element(mapped, last_location, true);
-            Some(last_size)
+        for (_, state) in self
+            .window_state
+            .iter_mut()
+            .filter(|(m, _)| m.windows().any(|(w, _)| &w == window))
+        {
+            state.maximized_around_pinned = false;
+            state.maximized_axis = false;
+        }
+        if let Some(mapped) = maybe_mapped {
+            let last_geometry = mapped.last_geometry.lock().unwrap().clone();
+            let Some(Rectangle {
+                loc: last_location,
+                size: last_size,
+            }) = last_geometry.or_else(|| self.centered_geometry(&mapped))
+            else {
+                return None;
+            };
+            let Some(output) = self
+                .space
+                .output_under(last_location.to_f64())
+                .next()
+                .or_else(|| self.space.outputs().next())
+                .cloned()
+            else {
+                return None;
+            };
+            let output_loc = self
+                .space
+                .output_geometry(&output)
+                .map(|g| g.loc)
+                .unwrap_or_default();
+            let offset = output.geometry().loc - output_loc;
+            if !self.place_animated(
+                &mapped,
+                &output,
+                Rectangle::from_loc_and_size(last_location - output_loc, last_size),
+            ) {
+                mapped.configure_geometry(Rectangle::from_loc_and_size(
+                    last_location + offset,
+                    last_size,
+                ));
+                self.space.map_element(mapped, last_location, true);
+            }
+            Some(last_size)
+        } else {
+            None
+        }
+    }
+
+    /// Captures the geometries of all windows, to be compared with a later snapshot.
+    #[cfg(feature = "debug")]
+    pub fn snapshot(&self) -> GeometrySnapshot {
+        GeometrySnapshot {
+            taken: Instant::now(),
+            windows: self
+                .space
+                .elements()
+                .filter_map(|elem| {
+                    self.space
+                        .element_geometry(elem)
+                        .map(|geo| (elem.clone(), elem.active_window().app_id(), geo))
+                })
+                .collect(),
+        }
+    }
+
+    /// Applies `action` to every window `predicate` matches, returning the number of windows.
+    pub fn for_each_matching(
+        &mut self,
+        predicate: impl Fn(&CosmicMapped) -> bool,
+        action: WindowAction,
+    ) -> usize {
+        let matching = self
+            .space
+            .elements()
+            .filter(|elem| predicate(elem))
+            .cloned()
+            .collect::<Vec<_>>();
+        for mapped in &matching {
+            match action {
+                WindowAction::Close => {
+                    let window = mapped.active_window();
+                    if self.close_request(&window) == CloseAction::Close {
+                        window.close();
+                    }
+                }
+                WindowAction::Minimize => {
+                    self.minimize(mapped);
+                }
+                WindowAction::SetPinned(pinned) => mapped.set_pinned(pinned),
+                WindowAction::SetHidden(hidden) => mapped.set_hidden(hidden),
+            }
+        }
+        matching.len()
+    }
+
+    /// Decides whether a close request for `window` may be forwarded to the client.
+    ///
+    /// Windows matching the `confirm_close` rules have their first request deferred and
+    /// get highlighted as a prompt, a second request confirms and closes them.
+    pub fn close_request(&mut self, window: &CosmicSurface) -> CloseAction {
+        if let Some(idx) = self.pending_close.iter().position(|(w, _)| w == window) {
+            self.pending_close.remove(idx);
+            return CloseAction::Close;
+        }
+
+        let app_id = window.app_id();
+        if self.config.confirm_close.iter().any(|id| id == &app_id) {
+            self.pending_close.push((window.clone(), Id::new()));
+            CloseAction::Defer
         } else {
-            None
+            CloseAction::Close
         }
     }
 
+    /// Drops a deferred close request of `window`, e.g. because the user moved on to another window.
+    pub fn cancel_close(&mut self, window: &CosmicSurface) {
+        self.pending_close.retain(|(w, _)| w != window);
+    }
+
     pub fn resize_request(
         &mut self,
         mapped: &CosmicMapped,
@@ -237,9 +1621,21 @@ impl FloatingLayout {
         start_data: PointerGrabStartData<State>,
         edges: ResizeEdge,
     ) -> Option<ResizeSurfaceGrab> {
+        if mapped.is_fixed_size() {
+            return None;
+        }
+
         if seat.get_pointer().is_some() {
-            let location = self.space.element_location(&mapped).unwrap();
+            self.remember_geometry(mapped);
+            // the window might have been unmapped since the request was made
+            let location = self.space.element_location(&mapped)?;
             let size = mapped.geometry().size;
+            // holding shift at the start of the resize keeps the current aspect ratio
+            let aspect_ratio = seat
+                .get_keyboard()
+                .filter(|keyboard| keyboard.modifier_state().shift)
+                .filter(|_| size.w > 0 && size.h > 0)
+                .map(|_| size.w as f64 / size.h as f64);
 
             Some(grabs::ResizeSurfaceGrab::new(
                 start_data,
@@ -247,12 +1643,15 @@ impl FloatingLayout {
                 edges,
                 location,
                 size,
+                self.min_size_floor(),
+                aspect_ratio,
             ))
         } else {
             None
         }
     }
 
+    /// Resizes the focused window at `edge` by `amount`, scaled by the output's scale.
     pub fn resize(
         &mut self,
         focused: &KeyboardFocusTarget,
@@ -270,63 +1669,142 @@ impl FloatingLayout {
         else {
             return false;
         };
+        if mapped.is_fixed_size() {
+            return false;
+        }
 
         let Some(original_geo) = self.space.element_geometry(mapped) else {
             return false; // we don't have that window
         };
+        self.remember_geometry(mapped);
         let mut geo = original_geo.clone();
 
+        let (min_size, max_size) = (mapped.min_size(), mapped.max_size());
+        let floor = self.min_size_floor();
+        let min_width = min_size.map(|s| s.w).unwrap_or(360).max(floor.w);
+        let min_height = min_size.map(|s| s.h).unwrap_or(240).max(floor.h);
+        // a limit of 0 means unlimited
+        let max_width = max_size
+            .map(|s| s.w)
+            .filter(|w| *w > 0)
+            .unwrap_or(i32::max_value());
+        let max_height = max_size
+            .map(|s| s.h)
+            .filter(|h| *h > 0)
+            .unwrap_or(i32::max_value());
+
+        let scale = self
+            .most_overlapped_output_for_element(mapped)
+            .map_or(1.0, |output| output.current_scale().fractional_scale());
+        let amount = (amount as f64 * scale).round() as i32;
+        // stop at the size limits, instead of moving the window past them
+        let step = |size: i32, min: i32, max: i32| match direction {
+            ResizeDirection::Inwards => -amount.min((size - min).max(0)),
+            ResizeDirection::Outwards => amount.min((max - size).max(0)),
+        };
+
         if edge.contains(ResizeEdge::RIGHT) || edge.contains(ResizeEdge::LEFT) {
-            if direction == ResizeDirection::Inwards {
-                geo.size.w -= amount;
-            } else {
-                geo.size.w += amount;
-            }
+            let step = step(geo.size.w, min_width, max_width);
+            geo.size.w += step;
             if edge.contains(ResizeEdge::LEFT) {
-                if direction == ResizeDirection::Inwards {
-                    geo.loc.x += amount;
-                } else {
-                    geo.loc.x -= amount;
-                }
+                geo.loc.x -= step;
             }
         }
         if edge.contains(ResizeEdge::BOTTOM) || edge.contains(ResizeEdge::TOP) {
-            if direction == ResizeDirection::Inwards {
-                geo.size.h -= amount;
-            } else {
-                geo.size.h += amount;
+            let step = step(geo.size.h, min_height, max_height);
+            geo.size.h += step;
+            if edge.contains(ResizeEdge::TOP) {
+                geo.loc.y -= step;
+            }
+        }
+
+        let grid = self.config.grid_size as i32;
+        if grid > 0 {
+            // move the resized edges onto the grid, in the direction of the resize
+            let origin = self
+                .most_overlapped_output_for_element(mapped)
+                .and_then(|output| self.space.output_geometry(&output))
+                .map(|geo| geo.loc)
+                .unwrap_or_default();
+            let (grow, shrink): (fn(f64) -> f64, fn(f64) -> f64) = match direction {
+                ResizeDirection::Outwards => (f64::ceil, f64::floor),
+                ResizeDirection::Inwards => (f64::floor, f64::ceil),
+            };
+            if edge.contains(ResizeEdge::LEFT) {
+                let right = geo.loc.x + geo.size.w;
+                geo.loc.x = align_to_grid(geo.loc.x - origin.x, grid, shrink) + origin.x;
+                geo.size.w = right - geo.loc.x;
+            } else if edge.contains(ResizeEdge::RIGHT) {
+                let right = align_to_grid(geo.loc.x + geo.size.w - origin.x, grid, grow) + origin.x;
+                geo.size.w = right - geo.loc.x;
             }
             if edge.contains(ResizeEdge::TOP) {
-                if direction == ResizeDirection::Inwards {
-                    geo.loc.y += amount;
-                } else {
-                    geo.loc.y -= amount;
-                }
+                let bottom = geo.loc.y + geo.size.h;
+                geo.loc.y = align_to_grid(geo.loc.y - origin.y, grid, shrink) + origin.y;
+                geo.size.h = bottom - geo.loc.y;
+            } else if edge.contains(ResizeEdge::BOTTOM) {
+                let bottom =
+                    align_to_grid(geo.loc.y + geo.size.h - origin.y, grid, grow) + origin.y;
+                geo.size.h = bottom - geo.loc.y;
             }
         }
 
-        let Some(bounding_box) = self
-            .space
-            .outputs()
-            .map(|o| self.space.output_geometry(o).unwrap())
-            .filter(|output_geo| output_geo.overlaps(geo))
-            .fold(None, |res, output_geo| match res {
-                None => Some(output_geo),
-                Some(other) => Some(other.merge(output_geo)),
-            })
-        else {
+        let bounding_box = if self.config.constrain_resize_to_output {
+            self.most_overlapped_output_for_element(mapped)
+                .and_then(|output| self.space.output_geometry(&output))
+        } else {
+            self.space
+                .outputs()
+                .map(|o| self.space.output_geometry(o).unwrap())
+                .filter(|output_geo| output_geo.overlaps(geo))
+                .fold(None, |res, output_geo| match res {
+                    None => Some(output_geo),
+                    Some(other) => Some(other.merge(output_geo)),
+                })
+        };
+        let Some(mut bounding_box) = bounding_box else {
             return true;
         };
-
-        let (min_size, max_size) = (mapped.min_size(), mapped.max_size());
-        let min_width = min_size.map(|s| s.w).unwrap_or(360);
-        let min_height = min_size.map(|s| s.h).unwrap_or(240);
-        let max_width = max_size.map(|s| s.w).unwrap_or(i32::max_value());
-        let max_height = max_size.map(|s| s.h).unwrap_or(i32::max_value());
+        // keep the border on the outputs as well
+        let border = self.border_width(mapped);
+        if bounding_box.size.w > border * 2 && bounding_box.size.h > border * 2 {
+            bounding_box.loc += Point::from((border, border));
+            bounding_box.size -= Size::from((border * 2, border * 2));
+        }
 
         geo.size.w = min_width.max(geo.size.w).min(max_width);
         geo.size.h = min_height.max(geo.size.h).min(max_height);
-        geo = geo.intersection(bounding_box).unwrap();
+        if mapped.is_window() {
+            geo.size = mapped.active_window().constrain_aspect(geo.size);
+        }
+        let Some(mut geo) = geo.intersection(bounding_box) else {
+            return false;
+        };
+
+        // the bounding box may span outputs of different heights, so growing upwards can
+        // still move the titlebar into the gap above the lower one
+        if edge.contains(ResizeEdge::TOP) {
+            let titlebar = Rectangle::from_loc_and_size(
+                geo.loc,
+                (geo.size.w, MIN_VISIBLE_TITLEBAR.min(geo.size.h)),
+            );
+            let needed_width = MIN_VISIBLE_TITLEBAR.min(geo.size.w);
+            let visible = self
+                .space
+                .outputs()
+                .filter_map(|o| self.space.output_geometry(o))
+                .filter_map(|output_geo| output_geo.intersection(titlebar))
+                .any(|visible| visible.size.w >= needed_width && visible.size.h == titlebar.size.h);
+            let output_top = self
+                .most_overlapped_output_for_element(mapped)
+                .and_then(|output| self.space.output_geometry(&output))
+                .map(|output_geo| output_geo.loc.y);
+            if let Some(top) = output_top.filter(|top| !visible && *top > geo.loc.y) {
+                let bottom = geo.loc.y + geo.size.h;
+                geo.loc.y = top;
+                geo.size.h = bottom - top;
+            }
+        }
 
         *mapped.resize_state.lock().unwrap() = Some(ResizeState::Resizing(ResizeData {
             edges: edge,
@@ -335,30 +1813,302 @@ impl FloatingLayout {
         }));
 
         mapped.set_resizing(true);
-        mapped.set_geometry(Rectangle::from_loc_and_size(
+        mapped.configure_geometry(Rectangle::from_loc_and_size(
             match mapped.active_window() {
                 CosmicSurface::X11(s) => s.geometry().loc,
                 _ => (0, 0).into(),
             },
             geo.size,
         ));
-        mapped.configure();
 
         true
     }
 
+    /// Moves the focused window by `dx` and `dy`, scaled by the output scale.
+    ///
+    /// The window stops once only [`MIN_VISIBLE_TITLEBAR`] of its titlebar would be left on any output.
+    pub fn move_window(&mut self, focused: &KeyboardFocusTarget, dx: i32, dy: i32) -> bool {
+        let Some(toplevel) = focused.toplevel() else {
+            return false;
+        };
+        let Some(mapped) = self
+            .space
+            .elements()
+            .find(|m| m.has_surface(&toplevel, WindowSurfaceType::TOPLEVEL))
+            .cloned()
+        else {
+            return false;
+        };
+        if mapped.is_maximized(false) || mapped.is_fullscreen(false) {
+            return false;
+        }
+        let Some(mut geo) = self.space.element_geometry(&mapped) else {
+            return false; // we don't have that window
+        };
+
+        let scale = self
+            .most_overlapped_output_for_element(&mapped)
+            .map_or(1.0, |output| output.current_scale().fractional_scale());
+        geo.loc.x += (dx as f64 * scale).round() as i32;
+        geo.loc.y += (dy as f64 * scale).round() as i32;
+
+        // keep a part of the titlebar on the output closest to the requested position
+        let titlebar_height = mapped.header_height().max(MIN_VISIBLE_TITLEBAR);
+        let visible = MIN_VISIBLE_TITLEBAR.min(geo.size.w);
+        let Some((output, location)) = self
+            .space
+            .outputs()
+            .filter_map(|output| {
+                let output_geo = self.space.output_geometry(output)?;
+                let x = geo.loc.x.clamp(
+                    output_geo.loc.x - geo.size.w + visible,
+                    output_geo.loc.x + output_geo.size.w - visible,
+                );
+                let y = geo.loc.y.clamp(
+                    output_geo.loc.y,
+                    (output_geo.loc.y + output_geo.size.h - titlebar_height).max(output_geo.loc.y),
+                );
+                Some((output.clone(), Point::<i32, Logical>::from((x, y)) - output_geo.loc))
+            })
+            .min_by_key(|(output, location)| {
+                let output_loc = self.space.output_geometry(output).unwrap().loc;
+                let distance = *location + output_loc - geo.loc;
+                distance.x.abs() + distance.y.abs()
+            })
+        else {
+            return false;
+        };
+
+        self.unsnap(&mapped);
+        self.set_window_geometry(
+            &mapped,
+            &output,
+            Rectangle::from_loc_and_size(location, geo.size),
+        )
+    }
+
     pub fn mapped(&self) -> impl Iterator<Item = &CosmicMapped> {
         self.space.elements().rev()
     }
 
+    /// Frame callback throttle factor for `elem`, depending on its focus and occlusion state.
+    pub fn frame_throttle(&self, elem: &CosmicMapped, focused: Option<&CosmicMapped>) -> u32 {
+        if focused == Some(elem) {
+            return 1;
+        }
+        if self.space.element_geometry(elem).is_none() {
+            return 1;
+        }
+
+        let occluded = self.visible_fraction(elem) <= 0.0;
+        if occluded && self.config.occluded_frame_throttle > 1 {
+            self.config.occluded_frame_throttle
+        } else {
+            self.config.unfocused_frame_throttle.max(1)
+        }
+    }
+
+    /// Fraction of `elem`'s area, that is on any output and not covered by windows stacked above it.
+    pub fn visible_fraction(&self, elem: &CosmicMapped) -> f32 {
+        let Some(geometry) = self.space.element_geometry(elem) else {
+            return 0.0;
+        };
+        if elem.is_hidden() || geometry.size.w <= 0 || geometry.size.h <= 0 {
+            return 0.0;
+        }
+
+        let occluders = self
+            .space
+            .elements()
+            .rev()
+            .take_while(|other| *other != elem)
+            .filter(|other| !other.is_hidden())
+            .filter_map(|other| self.space.element_geometry(other))
+            .collect::<Vec<_>>();
+
+        let visible_area = self
+            .space
+            .outputs_for_element(elem)
+            .iter()
+            .filter_map(|output| self.space.output_geometry(output))
+            .filter_map(|output_geo| output_geo.intersection(geometry))
+            .flat_map(|onscreen| onscreen.subtract_rects(occluders.iter().copied()))
+            .map(|rect| rect.size.w as i64 * rect.size.h as i64)
+            .sum::<i64>();
+
+        visible_area as f32 / (geometry.size.w as i64 * geometry.size.h as i64) as f32
+    }
+
+    /// The window covering all of `output` on its own, if nothing is drawn on top of it,
+    /// so that its buffers may be scanned out directly instead of being composited.
+    pub fn scanout_candidate(&self, output: &Output) -> Option<&CosmicMapped> {
+        let output_geo = self.space.output_geometry(output)?;
+        let top = self
+            .space
+            .elements_for_output(output)
+            .rev()
+            .find(|elem| !elem.is_hidden())?;
+        let geometry = self.space.element_geometry(top)?;
+
+        // decorations, borders or tints would still need to be composited
+        let undecorated = top.is_window()
+            && top.active_window().geometry().size == geometry.size
+            && top.tint().is_none()
+            && self.config.border_width == 0;
+        (undecorated && geometry.contains_rect(output_geo)).then_some(top)
+    }
+
     pub fn windows(&self) -> impl Iterator<Item = CosmicSurface> + '_ {
         self.mapped().flat_map(|e| e.windows().map(|(w, _)| w))
     }
 
+    /// Returns all mapped elements in the requested order, each paired with
+    /// a flag, that is set for the currently focused element.
+    pub fn windows_sorted_by(
+        &self,
+        criterion: SortBy,
+        focus_stack: &FocusStack<'_>,
+    ) -> Vec<(&CosmicMapped, bool)> {
+        let focused = focus_stack.last();
+        let mru = focus_stack.iter().collect::<Vec<_>>();
+        sort_windows(
+            self.mapped().collect(),
+            criterion,
+            &mru,
+            |elem| elem.active_window().app_id(),
+            |elem| {
+                self.most_overlapped_output_for_element(elem)
+                    .and_then(|o| self.space.output_geometry(&o))
+                    .map(|geo| geo.loc)
+            },
+            focused.as_ref(),
+        )
+    }
+
+    /// Window to move focus to from `from` in `direction`, judged by the windows' centers.
+    ///
+    /// Windows equally far away are ordered by when they were last focused.
+    pub fn focus_direction(
+        &self,
+        from: &CosmicMapped,
+        direction: Direction,
+        focus_stack: &FocusStack<'_>,
+    ) -> Option<CosmicMapped> {
+        let center = |geo: Rectangle<i32, Logical>| {
+            (
+                geo.loc.x as f64 + geo.size.w as f64 / 2.,
+                geo.loc.y as f64 + geo.size.h as f64 / 2.,
+            )
+        };
+        let origin = center(self.space.element_geometry(from)?);
+        // distance along `direction` and distance off its axis
+        let offset = |elem: &CosmicMapped| {
+            let (x, y) = center(self.space.element_geometry(elem).unwrap());
+            let (dx, dy) = (x - origin.0, y - origin.1);
+            match direction {
+                Direction::Left => (-dx, dy.abs()),
+                Direction::Right => (dx, dy.abs()),
+                Direction::Up => (-dy, dx.abs()),
+                Direction::Down => (dy, dx.abs()),
+            }
+        };
+        let mru = focus_stack.iter().collect::<Vec<_>>();
+        let recency =
+            |elem: &CosmicMapped| mru.iter().position(|m| *m == elem).unwrap_or(usize::MAX);
+
+        let output = self.most_overlapped_output_for_element(from);
+        let candidates = self
+            .space
+            .elements()
+            .filter(|elem| *elem != from && !elem.is_hidden())
+            .filter(|elem| {
+                self.config.focus_wrap == FocusWrap::CrossOutput
+                    || self.most_overlapped_output_for_element(elem) == output
+            })
+            .map(|elem| (elem, offset(elem)))
+            .collect::<Vec<_>>();
+
+        candidates
+            .iter()
+            .filter(|(_, (along, _))| *along > 0.)
+            .min_by_key(|(elem, (along, off_axis))| {
+                // prefer windows in line with the current one
+                ((along + off_axis * 2.).round() as i64, recency(elem))
+            })
+            .or_else(|| {
+                (self.config.focus_wrap == FocusWrap::Wrap)
+                    .then(|| {
+                        candidates.iter().min_by_key(|(elem, (along, off_axis))| {
+                            (along.round() as i64, off_axis.round() as i64, recency(elem))
+                        })
+                    })
+                    .flatten()
+            })
+            .map(|(elem, _)| (*elem).clone())
+    }
+
     pub fn refresh(&mut self) {
         #[cfg(feature = "debug")]
         puffin::profile_function!();
 
+        self.pending_close.retain(|(w, _)| w.alive());
+        self.tiled_arrangement.retain(|w| w.alive());
+        for (child, state) in self.window_state.iter_mut() {
+            if state
+                .swallowed
+                .as_ref()
+                .map_or(false, |parent| !parent.alive())
+            {
+                state.swallowed = None;
+            }
+            if !child.alive() {
+                if let Some(parent) = state.swallowed.take() {
+                    parent.set_hidden(false);
+                }
+            }
+        }
+        self.cascade_offsets
+            .retain(|output, _| self.space.outputs().any(|o| o == output));
+        self.place_deferred();
+        self.refresh_minimized();
+
+        let ttl = Duration::from_secs(self.config.closed_geometry_ttl);
+        for element in self
+            .space
+            .elements()
+            .filter(|e| !e.alive())
+            .cloned()
+            .collect::<Vec<_>>()
+        {
+            if let Some(app_id) = self
+                .window_state
+                .get(&element)
+                .and_then(|state| state.app_id.as_ref())
+            {
+                if !self.config.remember_flags.is_empty() {
+                    self.remembered_flags
+                        .insert(app_id.clone(), RememberedFlags::new(&element));
+                }
+                if self.config.persist_geometry {
+                    self.save_geometry(app_id, &element);
+                }
+            }
+            if let (Some(app_id), Some(geometry)) = (
+                self.window_state
+                    .remove(&element)
+                    .and_then(|state| state.app_id),
+                self.space.element_geometry(&element),
+            ) {
+                if !ttl.is_zero() {
+                    self.recently_closed
+                        .push((app_id, geometry, Instant::now()));
+                }
+            }
+        }
+        self.window_state.retain(|w, _| w.alive());
+        self.recently_closed
+            .retain(|(_, _, closed)| closed.elapsed() < ttl);
+
         self.space.refresh();
         for element in self
             .space
@@ -370,9 +2120,93 @@ impl FloatingLayout {
         {
             // TODO what about windows leaving to the top with no headerbar to drag? can that happen? (Probably if the user is moving outputs down)
             *element.last_geometry.lock().unwrap() = None;
-            let output = self.space.outputs().next().unwrap().clone();
+            let output = self
+                .most_overlapped_output_for_element(&element)
+                .unwrap_or_else(|| self.space.outputs().next().unwrap().clone());
             self.map_internal(element, &output, None);
         }
+        let output_layout = self
+            .space
+            .outputs()
+            .map(|o| (o.clone(), self.space.output_geometry(o).unwrap()))
+            .collect::<Vec<_>>();
+        if output_layout != self.output_layout {
+            self.output_layout = output_layout;
+            self.constrain_to_outputs();
+        }
+        for element in self.space.elements().cloned().collect::<Vec<_>>() {
+            self.update_home_output(&element);
+        }
+
+        let focused = self
+            .space
+            .elements()
+            .find(|e| e.is_activated(false))
+            .map(|e| (e.clone(), e.active_window().title()));
+        if let (Some((previous, previous_title)), Some((window, title))) =
+            (self.focused_title.as_ref(), focused.as_ref())
+        {
+            if previous == window && previous_title != title {
+                self.events.push(FloatingEvent::TitleChanged {
+                    window: window.clone(),
+                    title: title.clone(),
+                });
+            }
+        }
+        self.focused_title = focused;
+    }
+
+    /// Moves windows, which got partially out of view by changes to the outputs,
+    /// back into the work area of the output they are mostly on.
+    fn constrain_to_outputs(&mut self) {
+        for element in self
+            .space
+            .elements()
+            .filter(|e| !e.is_maximized(true) && !e.is_fullscreen(true))
+            .cloned()
+            .collect::<Vec<_>>()
+        {
+            let Some(geometry) = self.space.element_geometry(&element) else {
+                continue;
+            };
+            let visible = self
+                .space
+                .outputs()
+                .filter_map(|o| self.space.output_geometry(o)?.intersection(geometry))
+                .map(|overlap| overlap.size.w * overlap.size.h)
+                .sum::<i32>();
+            if visible >= geometry.size.w * geometry.size.h {
+                continue;
+            }
+            let Some(output) = self.most_overlapped_output_for_element(&element) else {
+                continue;
+            };
+            let Some(output_loc) = self.space.output_geometry(&output).map(|geo| geo.loc) else {
+                continue;
+            };
+
+            let area = self.work_area(&output, Some(&element));
+            let mut local = Rectangle::from_loc_and_size(geometry.loc - output_loc, geometry.size);
+            local.loc.x = local
+                .loc
+                .x
+                .min(area.loc.x + area.size.w - local.size.w)
+                .max(area.loc.x);
+            local.loc.y = local
+                .loc
+                .y
+                .min(area.loc.y + area.size.h - local.size.h)
+                .max(area.loc.y);
+            self.set_window_geometry(&element, &output, local);
+        }
+    }
+
+    /// Current title of `window`, if it is part of this layout.
+    pub fn title(&self, window: &CosmicMapped) -> Option<String> {
+        self.space
+            .elements()
+            .any(|e| e == window)
+            .then(|| window.active_window().title())
     }
 
     pub fn most_overlapped_output_for_element(&self, elem: &CosmicMapped) -> Option<Output> {
@@ -398,7 +2232,16 @@ impl FloatingLayout {
         )
     }
 
-    pub fn merge(&mut self, other: FloatingLayout) {
+    pub fn merge(
+        &mut self,
+        mut other: FloatingLayout,
+        toplevel_info: &mut ToplevelInfoState<State, CosmicSurface>,
+    ) {
+        let previous_outputs = other
+            .space
+            .elements()
+            .map(|element| (element.clone(), other.space.outputs_for_element(element)))
+            .collect::<Vec<_>>();
         let mut output_pos_map = HashMap::new();
         for output in self.space.outputs() {
             output_pos_map.insert(
@@ -412,6 +2255,10 @@ impl FloatingLayout {
             );
         }
         for element in other.space.elements() {
+            // sticky windows are only moved between workspaces, but never kept in two layouts
+            if self.space.elements().any(|e| e == element) {
+                continue;
+            }
             let mut elem_geo = other.space.element_geometry(element).unwrap();
             let output = other
                 .space
@@ -424,10 +2271,11 @@ impl FloatingLayout {
                     intersection.size.w * intersection.size.h
                 })
                 .unwrap_or(self.space.outputs().next().unwrap().clone());
-            elem_geo.loc += output_pos_map
+            let delta = output_pos_map
                 .get(&output)
                 .copied()
                 .unwrap_or_else(|| (0, 0).into());
+            elem_geo.loc += delta;
             let offset = output.geometry().loc
                 - self
                     .space
@@ -439,8 +2287,62 @@ impl FloatingLayout {
                 elem_geo.size,
             ));
             self.space.map_element(element.clone(), elem_geo.loc, false);
+
+            // state in space coordinates moves along with the element
+            if let Some(mut state) = other.window_state.remove(element) {
+                state.precise_location = state
+                    .precise_location
+                    .map(|location| location + delta.to_f64());
+                if let Some(animation) = state.geometry_animation.as_mut() {
+                    animation.from.loc += delta;
+                    animation.to.loc += delta;
+                }
+                self.window_state.insert(element.clone(), state);
+            }
         }
+        self.pending_close.extend(other.pending_close);
+        self.recently_closed.extend(other.recently_closed);
+        self.remembered_flags.extend(other.remembered_flags);
+        self.deferred_maps.extend(other.deferred_maps);
+        self.minimized.extend(other.minimized);
+        self.minimized_all.extend(other.minimized_all);
         self.refresh(); //fixup any out of bounds elements
+
+        for (element, previous) in previous_outputs {
+            let current = self.space.outputs_for_element(&element);
+            for (toplevel, _) in element.windows() {
+                for output in previous.iter().filter(|o| !current.contains(o)) {
+                    toplevel_info.toplevel_leave_output(&toplevel, output);
+                }
+                for output in current.iter().filter(|o| !previous.contains(o)) {
+                    toplevel_info.toplevel_enter_output(&toplevel, output);
+                }
+            }
+        }
+    }
+
+    /// Wallpaper luminance behind the focus indicator of `elem`, if adaptive contrast is enabled.
+    fn indicator_luminance(
+        &self,
+        output: &Output,
+        elem: &CosmicMapped,
+        geometry: Rectangle<i32, Logical>,
+    ) -> Option<f32> {
+        if !self.config.adaptive_indicator_contrast {
+            return None;
+        }
+
+        let mut cache = self.indicator_luminance.borrow_mut();
+        cache.retain(|elem, _| elem.alive());
+        if let Some((_, _, luminance)) = cache.get(elem).filter(|(cached_geo, sampled, _)| {
+            *cached_geo == geometry && sampled.elapsed() < LUMINANCE_CACHE_TTL
+        }) {
+            return Some(*luminance);
+        }
+
+        let luminance = background_luminance(output, geometry)?;
+        cache.insert(elem.clone(), (geometry, Instant::now(), luminance));
+        Some(luminance)
     }
 
     pub fn render_output<R>(
@@ -471,30 +2373,111 @@ impl FloatingLayout {
         let mut window_elements = Vec::new();
         let mut popup_elements = Vec::new();
 
-        self.space
-            .elements_for_output(output)
-            .rev()
+        self.top_down(self.space.elements_for_output(output))
+            .into_iter()
+            .filter(|elem| !elem.is_hidden())
             .for_each(|elem| {
-                let render_location = self.space.element_location(elem).unwrap()
-                    - output_geo.loc
-                    - elem.geometry().loc;
-                let (w_elements, p_elements) = elem.split_render_elements(
-                    renderer,
-                    render_location.to_physical_precise_round(output_scale),
-                    output_scale.into(),
-                    alpha,
+                let animated = self.animated_geometry(elem);
+                let location = animated
+                    .map(|geo| geo.loc)
+                    .unwrap_or_else(|| self.space.element_location(elem).unwrap());
+                let render_location = location - output_geo.loc - elem.geometry().loc;
+                let physical_location =
+                    match self.precise_location(elem).filter(|_| animated.is_none()) {
+                        Some(precise) => {
+                            (precise - output_geo.loc.to_f64() - elem.geometry().loc.to_f64())
+                                .to_physical(output_scale)
+                                .to_i32_round()
+                        }
+                        None => render_location.to_physical_precise_round(output_scale),
+                    };
+                let dim = if focused == Some(elem) {
+                    1.0
+                } else {
+                    self.config.inactive_dim.clamp(0.0, 1.0)
+                };
+                let (mut w_elements, mut p_elements) = elem
+                    .split_render_elements::<R, CosmicMappedRenderElement<R>>(
+                        renderer,
+                        physical_location,
+                        output_scale.into(),
+                        alpha * elem.opacity() * dim,
+                    );
+                if dim < 1.0 {
+                    // popups of dimmed windows stay fully visible
+                    p_elements = elem
+                        .split_render_elements::<R, CosmicMappedRenderElement<R>>(
+                            renderer,
+                            physical_location,
+                            output_scale.into(),
+                            alpha * elem.opacity(),
+                        )
+                        .1;
+                }
+                if let Some(animated) = animated {
+                    // stretch the current buffer until the client gets configured with the new size
+                    let size = elem.geometry().size;
+                    let scale = Scale::from((
+                        animated.size.w as f64 / size.w.max(1) as f64,
+                        animated.size.h as f64 / size.h.max(1) as f64,
+                    ));
+                    let origin = physical_location;
+                    w_elements = w_elements
+                        .into_iter()
+                        .map(|element| match element {
+                            CosmicMappedRenderElement::Window(elem) => {
+                                CosmicMappedRenderElement::GrabbedWindow(
+                                    RescaleRenderElement::from_element(elem, origin, scale),
+                                )
+                            }
+                            CosmicMappedRenderElement::Stack(elem) => {
+                                CosmicMappedRenderElement::GrabbedStack(
+                                    RescaleRenderElement::from_element(elem, origin, scale),
+                                )
+                            }
+                            x => x,
+                        })
+                        .collect();
+                }
+
+                let geometry = Rectangle::from_loc_and_size(
+                    location - output_geo.loc,
+                    animated.map_or_else(|| elem.geometry().size, |geo| geo.size),
                 );
+                let border_width = self.border_width(elem) as u8;
+                // radii are applied in physical pixels by the shaders
+                let scaled = |radius: u8| (radius as f64 * output_scale).round().min(255.) as u8;
+                // the border only follows radii set per window
+                let corner_radius = self.window_corner_radius(elem, 0);
+                let border_radius = if corner_radius > 0 {
+                    corner_radius.saturating_add(border_width)
+                } else {
+                    0
+                };
+                let border_geometry = {
+                    let width = border_width as i32;
+                    Rectangle::from_loc_and_size(
+                        geometry.loc - Point::from((width, width)),
+                        geometry.size + Size::from((width * 2, width * 2)),
+                    )
+                };
 
                 if focused == Some(elem) {
-                    let mut indicator_geometry = Rectangle::from_loc_and_size(
-                        self.space.element_location(elem).unwrap() - output_geo.loc,
-                        elem.geometry().size,
-                    );
+                    // keep indicators on the output, unless the window itself leaves it
+                    let bounds =
+                        Rectangle::from_loc_and_size((0, 0), output_geo.size).merge(geometry);
+                    let clamp =
+                        |geo: Rectangle<i32, Logical>| geo.intersection(bounds).unwrap_or(geo);
+                    let mut indicator_geometry = clamp(border_geometry);
 
                     if let Some((mode, resize)) = resize_indicator.as_mut() {
                         indicator_geometry.loc -= (18, 18).into();
                         indicator_geometry.size += (36, 36).into();
+                        indicator_geometry = clamp(indicator_geometry);
                         resize.resize(indicator_geometry.size);
+                        if resize.with_program(|internal| internal.set_size(geometry.size)) {
+                            resize.force_update();
+                        }
                         resize.output_enter(output, output_geo);
                         window_elements.extend(
                             resize
@@ -512,22 +2495,249 @@ impl FloatingLayout {
                     }
 
                     if indicator_thickness > 0 {
-                        let element = IndicatorShader::focus_element(
+                        let color = match self.indicator_luminance(output, elem, indicator_geometry)
+                        {
+                            Some(luminance) if luminance > 0.6 => FOCUS_INDICATOR_COLOR_DARK,
+                            _ => FOCUS_INDICATOR_COLOR,
+                        };
+                        let element = IndicatorShader::focus_element_with_color(
                             renderer,
                             elem.clone(),
                             indicator_geometry,
                             indicator_thickness,
+                            scaled(border_radius),
                             output_scale,
                             alpha,
+                            color,
                         );
                         window_elements.push(element.into());
                     }
                 }
 
+                if let Some(id) = self
+                    .window_state
+                    .get(elem)
+                    .and_then(|state| state.border_id.as_ref())
+                    .filter(|_| border_width > 0)
+                {
+                    let element = IndicatorShader::element(
+                        renderer,
+                        Key::Static(id.clone()),
+                        border_geometry,
+                        border_width,
+                        scaled(border_radius),
+                        alpha,
+                        output_scale,
+                        self.config.border_color,
+                    );
+                    window_elements.push(element.into());
+                }
+
+                // prompt to confirm a deferred close by closing again
+                if let Some((_, id)) = self
+                    .pending_close
+                    .iter()
+                    .find(|(w, _)| elem.windows().any(|(window, _)| &window == w))
+                {
+                    window_elements.push(
+                        BackdropShader::element(
+                            renderer,
+                            Key::Static(id.clone()),
+                            geometry,
+                            scaled(self.window_corner_radius(elem, self.corner_radius(output)))
+                                as f32,
+                            CLOSE_PROMPT_ALPHA * alpha,
+                            CLOSE_PROMPT_COLOR,
+                        )
+                        .into(),
+                    );
+                }
+
+                if let Some([r, g, b, a]) = elem.tint() {
+                    window_elements.push(
+                        BackdropShader::element(
+                            renderer,
+                            elem.clone(),
+                            geometry,
+                            scaled(self.window_corner_radius(elem, self.corner_radius(output)))
+                                as f32,
+                            a * alpha,
+                            [r, g, b],
+                        )
+                        .into(),
+                    );
+                }
+
                 window_elements.extend(w_elements);
+
+                let shadow = self.config.shadow;
+                if elem.shadow(shadow.enabled)
+                    && !elem.is_maximized(false)
+                    && !elem.is_fullscreen(false)
+                {
+                    let shadow_geometry = Rectangle::from_loc_and_size(
+                        geometry.loc + Point::from(shadow.offset),
+                        geometry.size,
+                    );
+                    let shadow_alpha = if focused == Some(elem) {
+                        shadow.focused_alpha
+                    } else {
+                        shadow.alpha
+                    };
+                    window_elements.push(
+                        ShadowShader::element(
+                            renderer,
+                            elem.clone(),
+                            shadow_geometry,
+                            corner_radius as f32,
+                            shadow.blur_radius,
+                            output_scale,
+                            shadow_alpha * alpha,
+                            shadow.color,
+                        )
+                        .into(),
+                    );
+                }
                 popup_elements.extend(p_elements);
             });
 
         (window_elements, popup_elements)
     }
 }
+
+/// Geometry to restore on unmaximize, after a maximize request for a window currently at `current`.
+///
+/// A repeated request must not overwrite the geometry to restore with the maximized one.
+fn stash_for_maximize(
+    stashed: Option<Rectangle<i32, Logical>>,
+    current: Rectangle<i32, Logical>,
+    maximized: bool,
+) -> Option<Rectangle<i32, Logical>> {
+    if maximized {
+        stashed
+    } else {
+        Some(current)
+    }
+}
+
+/// `elements` given top-most first, sorted by `criterion` and each paired with a flag set for `focused`.
+///
+/// `mru` lists the recently focused elements, the most recent one first.
+fn sort_windows<T: PartialEq>(
+    mut elements: Vec<T>,
+    criterion: SortBy,
+    mru: &[T],
+    app_id: impl Fn(&T) -> String,
+    output_location: impl Fn(&T) -> Option<Point<i32, Logical>>,
+    focused: Option<&T>,
+) -> Vec<(T, bool)> {
+    match criterion {
+        SortBy::StackOrder => {}
+        SortBy::Mru => {
+            elements.sort_by_key(|elem| mru.iter().position(|m| m == elem).unwrap_or(usize::MAX));
+        }
+        SortBy::AppId => elements.sort_by_cached_key(|elem| app_id(elem)),
+        SortBy::Output => elements.sort_by_cached_key(|elem| {
+            output_location(elem).map_or((i32::MAX, i32::MAX), |loc| (loc.x, loc.y))
+        }),
+    }
+
+    elements
+        .into_iter()
+        .map(|elem| {
+            let is_focused = Some(&elem) == focused;
+            (elem, is_focused)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_windows_by_every_criterion() {
+        // stacking order, top-most first
+        let elements = vec!["editor", "terminal", "browser", "mail"];
+        let mru = ["browser", "editor"];
+        let app_id = |elem: &&str| match *elem {
+            "editor" | "terminal" => String::from("b-tools"),
+            _ => String::from("a-web"),
+        };
+        let output_location = |elem: &&str| match *elem {
+            "editor" => Some(Point::from((1920, 0))),
+            "terminal" => Some(Point::from((0, 0))),
+            "browser" => Some(Point::from((1920, 0))),
+            _ => None,
+        };
+        let sort = |criterion| {
+            sort_windows(
+                elements.clone(),
+                criterion,
+                &mru,
+                app_id,
+                output_location,
+                Some(&"browser"),
+            )
+        };
+
+        assert_eq!(
+            sort(SortBy::StackOrder),
+            vec![
+                ("editor", false),
+                ("terminal", false),
+                ("browser", true),
+                ("mail", false)
+            ]
+        );
+        // never focused windows keep their stacking order behind the others
+        assert_eq!(
+            sort(SortBy::Mru),
+            vec![
+                ("browser", true),
+                ("editor", false),
+                ("terminal", false),
+                ("mail", false)
+            ]
+        );
+        // the sort is stable, so windows of the same app stay in stacking order
+        assert_eq!(
+            sort(SortBy::AppId),
+            vec![
+                ("browser", true),
+                ("mail", false),
+                ("editor", false),
+                ("terminal", false)
+            ]
+        );
+        // windows without an output come last
+        assert_eq!(
+            sort(SortBy::Output),
+            vec![
+                ("terminal", false),
+                ("editor", false),
+                ("browser", true),
+                ("mail", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn maximize_round_trip_after_move() {
+        let floating = Rectangle::from_loc_and_size((100, 100), (800, 600));
+        let maximized = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+
+        // maximize, including a repeated request while already maximized
+        let stashed = stash_for_maximize(None, floating, false);
+        let stashed = stash_for_maximize(stashed, maximized, true);
+        assert_eq!(stashed, Some(floating));
+
+        // unmaximize restores the stashed geometry, then the window gets moved
+        let moved = Rectangle::from_loc_and_size((400, 300), stashed.unwrap().size);
+
+        // maximize again, the second unmaximize has to restore the moved geometry
+        let stashed = stash_for_maximize(stashed, moved, false);
+        let stashed = stash_for_maximize(stashed, maximized, true);
+        assert_eq!(stashed, Some(moved));
+    }
+}
@@ -0,0 +1,355 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use serde::{Deserialize, Serialize};
+use smithay::{
+    desktop::layer_map_for_output,
+    output::Output,
+    utils::{Logical, Rectangle},
+};
+use std::{fs::File, path::PathBuf};
+use tracing::warn;
+
+use crate::{config::RememberedFlag, shell::element::CosmicMapped};
+
+use super::{
+    geometry_store::{GeometryStore, SavedGeometry},
+    placement::clamp_into,
+    FloatingLayout,
+};
+
+/// Version of the [`LayoutExport`] schema, bumped on incompatible changes
+pub const LAYOUT_EXPORT_VERSION: u32 = 1;
+
+/// Human-readable description of a floating layout, e.g. for bug reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutExport {
+    pub version: u32,
+    pub outputs: Vec<OutputLayoutExport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputLayoutExport {
+    pub name: String,
+    /// Windows mostly shown on this output, bottom-most first
+    pub windows: Vec<WindowExport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowExport {
+    pub app_id: String,
+    pub title: String,
+    /// Output-local location and size (x, y, width, height)
+    pub geometry: (i32, i32, i32, i32),
+    #[serde(default)]
+    pub stacked: bool,
+    #[serde(default)]
+    pub maximized: bool,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl LayoutExport {
+    fn path() -> Option<PathBuf> {
+        xdg::BaseDirectories::new()
+            .ok()?
+            .place_state_file("cosmic-comp/floating_layout.ron")
+            .ok()
+    }
+
+    /// Writes the layout to the state directory, replacing the previously saved one.
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            return;
+        };
+        let writer = match File::create(&path) {
+            Ok(writer) => writer,
+            Err(err) => {
+                warn!(?err, "Failed to export layout to {}.", path.display());
+                return;
+            }
+        };
+        if let Err(err) = ron::ser::to_writer_pretty(writer, self, Default::default()) {
+            warn!(?err, "Failed to export layout to {}.", path.display());
+        }
+    }
+
+    /// Reads the layout last written by [`LayoutExport::save`].
+    pub fn load() -> Option<LayoutExport> {
+        let path = Self::path()?;
+        let reader = File::open(&path)
+            .map_err(|err| warn!(?err, "Failed to open {}.", path.display()))
+            .ok()?;
+        ron::de::from_reader(reader)
+            .map_err(|err| warn!(?err, "Failed to read layout from {}.", path.display()))
+            .ok()
+    }
+}
+
+/// Flags of a closed window, which may be re-applied to the next window of the same app
+#[derive(Debug, Clone)]
+pub(super) struct RememberedFlags {
+    pinned: bool,
+    tint: Option<[f32; 4]>,
+    corner_radius: Option<u8>,
+}
+
+impl RememberedFlags {
+    pub(super) fn new(mapped: &CosmicMapped) -> RememberedFlags {
+        RememberedFlags {
+            pinned: mapped.is_pinned(),
+            tint: mapped.tint(),
+            corner_radius: mapped.corner_radius(),
+        }
+    }
+}
+
+impl FloatingLayout {
+    /// Geometry saved for `app_id` on disk, on its previous output if still connected or on `fallback`.
+    ///
+    /// The geometry is moved and shrunk to fit into the non-exclusive zone, if necessary.
+    pub(super) fn saved_geometry(
+        &self,
+        app_id: &str,
+        fallback: &Output,
+    ) -> Option<Rectangle<i32, Logical>> {
+        let saved = GeometryStore::get(app_id)?;
+        let output = self
+            .space
+            .outputs()
+            .find(|o| o.name() == saved.output)
+            .unwrap_or(fallback);
+        let output_loc = self.space.output_geometry(output)?.loc;
+
+        let mut geometry = clamp_into(
+            saved.geometry(),
+            layer_map_for_output(output).non_exclusive_zone(),
+        );
+        geometry.loc += output_loc;
+        Some(geometry)
+    }
+
+    /// Re-applies the flags remembered for `app_id`, which are configured to be remembered.
+    pub(super) fn restore_flags(&self, mapped: &CosmicMapped, app_id: &str) {
+        let Some(flags) = self.remembered_flags.get(app_id) else {
+            return;
+        };
+        for flag in &self.config.remember_flags {
+            match flag {
+                RememberedFlag::Pinned => mapped.set_pinned(flags.pinned),
+                RememberedFlag::Tint => mapped.set_tint(flags.tint),
+                RememberedFlag::CornerRadius => mapped.set_corner_radius(flags.corner_radius),
+            }
+        }
+    }
+
+    /// Describes the current arrangement of windows per output.
+    pub fn export(&self) -> LayoutExport {
+        let mut outputs = self
+            .space
+            .outputs()
+            .map(|output| OutputLayoutExport {
+                name: output.name(),
+                windows: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        for elem in self.space.elements() {
+            let (Some(output), Some(geometry)) = (
+                self.most_overlapped_output_for_element(elem),
+                self.space.element_geometry(elem),
+            ) else {
+                continue;
+            };
+            let output_loc = self.space.output_geometry(&output).unwrap().loc;
+            let window = elem.active_window();
+            if let Some(export) = outputs.iter_mut().find(|o| o.name == output.name()) {
+                export.windows.push(WindowExport {
+                    app_id: window.app_id(),
+                    title: window.title(),
+                    geometry: (
+                        geometry.loc.x - output_loc.x,
+                        geometry.loc.y - output_loc.y,
+                        geometry.size.w,
+                        geometry.size.h,
+                    ),
+                    stacked: elem.is_stack(),
+                    maximized: elem.is_maximized(true),
+                    hidden: elem.is_hidden(),
+                    pinned: elem.is_pinned(),
+                });
+            }
+        }
+
+        LayoutExport {
+            version: LAYOUT_EXPORT_VERSION,
+            outputs,
+        }
+    }
+
+    /// Rearranges windows according to `layout`, matching them by app id (and title, if possible).
+    ///
+    /// Windows without a match and outputs, that don't exist anymore, are skipped.
+    /// Maximized windows are placed at their exported geometry, but not maximized again.
+    /// Returns the number of windows that were placed.
+    pub fn import(&mut self, layout: &LayoutExport) -> usize {
+        if layout.version > LAYOUT_EXPORT_VERSION {
+            warn!(
+                version = layout.version,
+                "Unsupported layout version, ignoring layout."
+            );
+            return 0;
+        }
+
+        let mut unmatched = self.space.elements().cloned().collect::<Vec<_>>();
+        let mut placed = 0;
+        for output_layout in &layout.outputs {
+            let Some(output) = self
+                .space
+                .outputs()
+                .find(|o| o.name() == output_layout.name)
+                .cloned()
+            else {
+                continue;
+            };
+
+            // bottom-most first, so placing each window on top restores the stacking order
+            for (mapped, export) in match_exports(
+                &output_layout.windows,
+                &mut unmatched,
+                |mapped| mapped.active_window().app_id(),
+                |mapped| mapped.active_window().title(),
+            ) {
+                let (x, y, w, h) = export.geometry;
+
+                mapped.set_pinned(export.pinned);
+                mapped.set_hidden(export.hidden);
+                self.place(
+                    mapped,
+                    &output,
+                    Rectangle::from_loc_and_size((x, y), (w, h)),
+                );
+                placed += 1;
+            }
+        }
+        placed
+    }
+
+    /// Saves the geometry of the closed `element` to disk, to be restored by the next window of `app_id`.
+    pub(super) fn save_geometry(&self, app_id: &str, element: &CosmicMapped) {
+        let Some(output) = self.most_overlapped_output_for_element(element) else {
+            return;
+        };
+        // maximized windows should open again at their regular size
+        let geometry = if element.is_maximized(false) {
+            *element.last_geometry.lock().unwrap()
+        } else {
+            self.space.element_geometry(element)
+        };
+        if let (Some(mut geometry), Some(output_geo)) =
+            (geometry, self.space.output_geometry(&output))
+        {
+            geometry.loc -= output_geo.loc;
+            GeometryStore::save(
+                app_id.to_string(),
+                SavedGeometry::new(output.name(), geometry),
+            );
+        }
+    }
+}
+
+/// Pairs each of `exports` with an element of the same app id from `unmatched`, removing it from there.
+///
+/// Elements with the same title are preferred, but any element of the app is used otherwise.
+/// Exports without a match are left out.
+fn match_exports<'a, T>(
+    exports: &'a [WindowExport],
+    unmatched: &mut Vec<T>,
+    app_id: impl Fn(&T) -> String,
+    title: impl Fn(&T) -> String,
+) -> Vec<(T, &'a WindowExport)> {
+    let mut matches = exports
+        .iter()
+        .map(|export| {
+            let idx = unmatched
+                .iter()
+                .position(|elem| app_id(elem) == export.app_id && title(elem) == export.title)?;
+            Some(unmatched.remove(idx))
+        })
+        .collect::<Vec<_>>();
+    for (slot, export) in matches.iter_mut().zip(exports) {
+        if slot.is_none() {
+            *slot = unmatched
+                .iter()
+                .position(|elem| app_id(elem) == export.app_id)
+                .map(|idx| unmatched.remove(idx));
+        }
+    }
+    matches
+        .into_iter()
+        .zip(exports)
+        .filter_map(|(elem, export)| Some((elem?, export)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_export_round_trip() {
+        let window = |app_id: &str, title: &str, geometry| WindowExport {
+            app_id: app_id.into(),
+            title: title.into(),
+            geometry,
+            stacked: false,
+            maximized: false,
+            hidden: false,
+            pinned: true,
+        };
+        let layout = LayoutExport {
+            version: LAYOUT_EXPORT_VERSION,
+            outputs: vec![OutputLayoutExport {
+                name: String::from("DP-1"),
+                windows: vec![
+                    window("term", "shell", (0, 0, 640, 480)),
+                    window("term", "logs", (640, 0, 640, 480)),
+                    window("editor", "notes", (0, 480, 800, 600)),
+                    window("chat", "general", (800, 480, 400, 600)),
+                ],
+            }],
+        };
+
+        let serialized = ron::ser::to_string_pretty(&layout, Default::default()).unwrap();
+        let imported: LayoutExport = ron::de::from_str(&serialized).unwrap();
+        assert_eq!(imported.version, LAYOUT_EXPORT_VERSION);
+        assert_eq!(imported.outputs[0].name, "DP-1");
+
+        // titles of the running windows may have changed since the export
+        let mut unmatched = vec![
+            ("editor", "draft"),
+            ("term", "logs"),
+            ("term", "build"),
+            ("mail", "inbox"),
+        ];
+        let matched = match_exports(
+            &imported.outputs[0].windows,
+            &mut unmatched,
+            |(app_id, _)| app_id.to_string(),
+            |(_, title)| title.to_string(),
+        );
+        assert_eq!(
+            matched
+                .iter()
+                .map(|(elem, export)| (*elem, export.geometry, export.pinned))
+                .collect::<Vec<_>>(),
+            vec![
+                (("term", "build"), (0, 0, 640, 480), true),
+                (("term", "logs"), (640, 0, 640, 480), true),
+                (("editor", "draft"), (0, 480, 800, 600), true),
+            ]
+        );
+        assert_eq!(unmatched, vec![("mail", "inbox")]);
+    }
+}
@@ -0,0 +1,670 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use serde::Deserialize;
+use smithay::{
+    desktop::{layer_map_for_output, space::SpaceElement},
+    output::Output,
+    utils::{Logical, Point, Rectangle, Size},
+};
+
+use crate::{
+    shell::{element::CosmicMapped, focus::target::KeyboardFocusTarget, CosmicSurface},
+    state::State,
+    utils::prelude::*,
+    wayland::protocols::toplevel_info::ToplevelInfoState,
+};
+
+use super::FloatingLayout;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ScreenRegion {
+    LeftHalf,
+    RightHalf,
+    TopHalf,
+    BottomHalf,
+    TopLeftQuarter,
+    TopRightQuarter,
+    BottomLeftQuarter,
+    BottomRightQuarter,
+    LeftThird,
+    CenterThird,
+    RightThird,
+    LeftTwoThirds,
+    RightTwoThirds,
+    Center,
+}
+
+impl ScreenRegion {
+    /// Location and size of the region as fractions of the work area
+    fn fractions(&self) -> (f64, f64, f64, f64) {
+        match self {
+            ScreenRegion::LeftHalf => (0.0, 0.0, 0.5, 1.0),
+            ScreenRegion::RightHalf => (0.5, 0.0, 0.5, 1.0),
+            ScreenRegion::TopHalf => (0.0, 0.0, 1.0, 0.5),
+            ScreenRegion::BottomHalf => (0.0, 0.5, 1.0, 0.5),
+            ScreenRegion::TopLeftQuarter => (0.0, 0.0, 0.5, 0.5),
+            ScreenRegion::TopRightQuarter => (0.5, 0.0, 0.5, 0.5),
+            ScreenRegion::BottomLeftQuarter => (0.0, 0.5, 0.5, 0.5),
+            ScreenRegion::BottomRightQuarter => (0.5, 0.5, 0.5, 0.5),
+            ScreenRegion::LeftThird => (0.0, 0.0, 1.0 / 3.0, 1.0),
+            ScreenRegion::CenterThird => (1.0 / 3.0, 0.0, 1.0 / 3.0, 1.0),
+            ScreenRegion::RightThird => (2.0 / 3.0, 0.0, 1.0 / 3.0, 1.0),
+            ScreenRegion::LeftTwoThirds => (0.0, 0.0, 2.0 / 3.0, 1.0),
+            ScreenRegion::RightTwoThirds => (1.0 / 3.0, 0.0, 2.0 / 3.0, 1.0),
+            ScreenRegion::Center => (1.0 / 6.0, 1.0 / 6.0, 2.0 / 3.0, 2.0 / 3.0),
+        }
+    }
+
+    fn geometry(&self, work_area: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        let (x, y, w, h) = self.fractions();
+        let (area_w, area_h) = (work_area.size.w as f64, work_area.size.h as f64);
+        Rectangle::from_loc_and_size(
+            (
+                work_area.loc.x + (area_w * x).round() as i32,
+                work_area.loc.y + (area_h * y).round() as i32,
+            ),
+            ((area_w * w).round() as i32, (area_h * h).round() as i32),
+        )
+    }
+}
+
+/// Half or quarter of an output a floating window can be snapped to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SnapTarget {
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl SnapTarget {
+    pub(in crate::shell) fn region(&self) -> ScreenRegion {
+        match self {
+            SnapTarget::Left => ScreenRegion::LeftHalf,
+            SnapTarget::Right => ScreenRegion::RightHalf,
+            SnapTarget::TopLeft => ScreenRegion::TopLeftQuarter,
+            SnapTarget::TopRight => ScreenRegion::TopRightQuarter,
+            SnapTarget::BottomLeft => ScreenRegion::BottomLeftQuarter,
+            SnapTarget::BottomRight => ScreenRegion::BottomRightQuarter,
+        }
+    }
+}
+
+/// Direction a floating window can be maximized in, while keeping its size in the other one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl FloatingLayout {
+    /// Output-local geometry of `mapped`, when placed into `region` of the work area of `output`.
+    ///
+    /// Respects the size limits of the window, but never leaves the non-exclusive zone.
+    pub(in crate::shell) fn region_geometry(
+        &self,
+        mapped: &CosmicMapped,
+        output: &Output,
+        region: ScreenRegion,
+    ) -> Rectangle<i32, Logical> {
+        let work_area = self.work_area(output, Some(mapped));
+        let mut geometry = self.shrink_by_border(region.geometry(work_area));
+
+        let (min_size, max_size) = (mapped.min_size(), mapped.max_size());
+        if let Some(min_size) = min_size {
+            geometry.size.w = geometry.size.w.max(min_size.w);
+            geometry.size.h = geometry.size.h.max(min_size.h);
+        }
+        if let Some(max_size) = max_size {
+            let mut size = geometry.size;
+            if max_size.w > 0 {
+                size.w = size.w.min(max_size.w);
+            }
+            if max_size.h > 0 {
+                size.h = size.h.min(max_size.h);
+            }
+            // keep the clamped window centered in its region
+            geometry.loc.x += (geometry.size.w - size.w) / 2;
+            geometry.loc.y += (geometry.size.h - size.h) / 2;
+            geometry.size = size;
+        }
+
+        clamp_into(geometry, layer_map_for_output(output).non_exclusive_zone())
+    }
+
+    /// Snaps `mapped` to a half or quarter of `output`.
+    ///
+    /// The geometry from before snapping is restored, once the window gets dragged again.
+    pub(in crate::shell) fn snap_to(
+        &mut self,
+        mapped: &CosmicMapped,
+        output: &Output,
+        snap: SnapTarget,
+    ) -> bool {
+        let Some(original_geo) = self.space.element_geometry(mapped) else {
+            return false;
+        };
+        if !self.is_snapped(mapped) {
+            *mapped.last_geometry.lock().unwrap() = Some(original_geo);
+        }
+        let geometry = self.region_geometry(mapped, output, snap.region());
+        self.window_state.entry(mapped.clone()).or_default().snapped = true;
+        self.place(mapped.clone(), output, geometry);
+        true
+    }
+
+    /// Moves `mapped` to the center of the work area of `output`, without changing its size.
+    pub fn center_request(&mut self, mapped: &CosmicMapped, output: &Output) -> bool {
+        if mapped.is_maximized(true) || !self.space.elements().any(|e| e == mapped) {
+            return false;
+        }
+        let area = self.shrink_by_border(self.work_area(output, Some(mapped)));
+        let size = mapped.geometry().size;
+        let location = Point::from((
+            area.loc.x + (area.size.w / 2) - (size.w / 2),
+            area.loc.y + (area.size.h / 2) - (size.h / 2),
+        ));
+
+        self.unsnap(mapped);
+        self.place(
+            mapped.clone(),
+            output,
+            Rectangle::from_loc_and_size(location, size),
+        );
+        true
+    }
+
+    /// Size `mapped` had before it got snapped, if it is currently snapped.
+    pub fn snapped_size(&self, mapped: &CosmicMapped) -> Option<Size<i32, Logical>> {
+        if !self.is_snapped(mapped) {
+            return None;
+        }
+        mapped.last_geometry.lock().unwrap().map(|geo| geo.size)
+    }
+
+    /// Moves the focused window into the given region of the work area of its output.
+    pub fn place_region(&mut self, focused: &KeyboardFocusTarget, region: ScreenRegion) -> bool {
+        let Some(mapped) = self.element_for_focus(focused).cloned() else {
+            return false;
+        };
+        let Some(output) = self.most_overlapped_output_for_element(&mapped) else {
+            return false;
+        };
+        let geometry = self.region_geometry(&mapped, &output, region);
+        self.tiled_arrangement.retain(|w| w != &mapped);
+        self.remember_geometry(&mapped);
+        self.place(mapped, &output, geometry);
+        true
+    }
+
+    /// Arranges the up to four most recently focused windows on `output` into its quarters.
+    ///
+    /// The previous geometries can be restored with [`FloatingLayout::undo_placement`].
+    pub fn quarter_tile(&mut self, output: &Output) -> bool {
+        if self.space.output_geometry(output).is_none() {
+            return false;
+        }
+        // focusing raises windows, so the stacking order is the focus order
+        let windows = self
+            .top_down(self.space.elements_for_output(output))
+            .into_iter()
+            .filter(|elem| {
+                !elem.is_hidden() && !elem.is_maximized(false) && !elem.is_fullscreen(false)
+            })
+            .filter(|elem| self.most_overlapped_output_for_element(elem).as_ref() == Some(output))
+            .take(4)
+            .cloned()
+            .collect::<Vec<_>>();
+        if windows.is_empty() {
+            return false;
+        }
+
+        let quarters = [
+            SnapTarget::TopLeft,
+            SnapTarget::TopRight,
+            SnapTarget::BottomLeft,
+            SnapTarget::BottomRight,
+        ];
+        for (mapped, quarter) in windows.iter().zip(quarters) {
+            self.remember_geometry(mapped);
+            let geometry = self.region_geometry(mapped, output, quarter.region());
+            self.unsnap(mapped);
+            self.set_window_geometry(mapped, output, geometry);
+        }
+        self.tiled_arrangement = windows;
+        true
+    }
+
+    /// Output-local geometry windows on `output` get maximized to, keeping the configured gap.
+    pub fn maximized_geometry(&self, output: &Output) -> Rectangle<i32, Logical> {
+        let area = output.safe_area();
+        let gap = self.maximize_gap(output) as i32;
+        if area.size.w <= gap * 2 || area.size.h <= gap * 2 {
+            return area;
+        }
+        Rectangle::from_loc_and_size(
+            area.loc + Point::from((gap, gap)),
+            area.size - Size::from((gap * 2, gap * 2)),
+        )
+    }
+
+    /// Maximizes `window` into the largest part of the work area not covered by pinned windows.
+    ///
+    /// Returns `false` if not configured to do so, or if there are no pinned windows on `output`,
+    /// in which case the window should be maximized as usual.
+    pub fn maximize_around_pinned(&mut self, window: &CosmicSurface, output: &Output) -> bool {
+        if !self.config.maximize_around_pinned {
+            return false;
+        }
+        let Some(mapped) = self
+            .space
+            .elements()
+            .find(|m| m.windows().any(|(w, _)| &w == window))
+            .cloned()
+        else {
+            return false;
+        };
+        let Some(output_geo) = self.space.output_geometry(output) else {
+            return false;
+        };
+
+        let pinned = self
+            .space
+            .elements_for_output(output)
+            .filter(|elem| *elem != &mapped && elem.is_pinned() && !elem.is_hidden())
+            .filter_map(|elem| self.space.element_geometry(elem))
+            .map(|mut geo| {
+                geo.loc -= output_geo.loc;
+                geo
+            })
+            .collect::<Vec<_>>();
+        if pinned.is_empty() {
+            return false;
+        }
+
+        let work_area = self
+            .work_area(output, Some(&mapped))
+            .intersection(self.maximized_geometry(output))
+            .unwrap_or_else(|| self.work_area(output, Some(&mapped)));
+        let Some(geometry) = largest_free_rect(work_area, &pinned) else {
+            return false;
+        };
+        self.window_state
+            .entry(mapped.clone())
+            .or_default()
+            .maximized_around_pinned = true;
+        if !self.place_animated(&mapped, output, geometry) {
+            self.place(mapped, output, geometry);
+        }
+        true
+    }
+
+    pub fn is_maximized_around_pinned(&self, window: &CosmicSurface) -> bool {
+        self.window_state.iter().any(|(m, state)| {
+            state.maximized_around_pinned && m.windows().any(|(w, _)| &w == window)
+        })
+    }
+
+    /// Grows `window` to fill the work area along `axis`, keeping its size in the other direction.
+    ///
+    /// The previous geometry is restored by [`FloatingLayout::unmaximize_request`].
+    pub fn maximize_axis(&mut self, window: &CosmicSurface, axis: Axis) -> bool {
+        let Some(mapped) = self
+            .space
+            .elements()
+            .find(|m| m.windows().any(|(w, _)| &w == window))
+            .cloned()
+        else {
+            return false;
+        };
+        if mapped.is_maximized(true) || mapped.is_fullscreen(true) {
+            return false;
+        }
+        let Some(output) = self.most_overlapped_output_for_element(&mapped) else {
+            return false;
+        };
+        let (Some(mut geometry), Some(output_geo)) = (
+            self.space.element_geometry(&mapped),
+            self.space.output_geometry(&output),
+        ) else {
+            return false;
+        };
+
+        // snapped windows already stashed the geometry from before snapping
+        let maximized_axis = self
+            .window_state
+            .get(&mapped)
+            .map_or(false, |state| state.maximized_axis);
+        if !maximized_axis && !self.unsnap(&mapped) {
+            self.remember_geometry(&mapped);
+            *mapped.last_geometry.lock().unwrap() = Some(geometry);
+        }
+        geometry.loc -= output_geo.loc;
+
+        let area = self.shrink_by_border(self.work_area(&output, Some(&mapped)));
+        let (min_size, max_size) = (mapped.min_size(), mapped.max_size());
+        let fit = |extent: i32, min: Option<i32>, max: Option<i32>| {
+            let extent = max
+                .filter(|max| *max > 0)
+                .map_or(extent, |max| extent.min(max));
+            min.map_or(extent, |min| extent.max(min))
+        };
+        match axis {
+            Axis::Horizontal => {
+                let width = fit(area.size.w, min_size.map(|s| s.w), max_size.map(|s| s.w));
+                geometry.loc.x = area.loc.x + (area.size.w - width) / 2;
+                geometry.size.w = width;
+            }
+            Axis::Vertical => {
+                let height = fit(area.size.h, min_size.map(|s| s.h), max_size.map(|s| s.h));
+                geometry.loc.y = area.loc.y + (area.size.h - height) / 2;
+                geometry.size.h = height;
+            }
+        }
+
+        self.window_state
+            .entry(mapped.clone())
+            .or_default()
+            .maximized_axis = true;
+        self.place(mapped, &output, geometry);
+        true
+    }
+
+    pub fn is_maximized_axis(&self, window: &CosmicSurface) -> bool {
+        self.window_state
+            .iter()
+            .any(|(m, state)| state.maximized_axis && m.windows().any(|(w, _)| &w == window))
+    }
+
+    /// Geometry in space coordinates a window gets, when it is centered on the output it is mostly on.
+    ///
+    /// Used when a window has to be restored without a previous geometry, e.g. because it was mapped maximized.
+    pub(super) fn centered_geometry(
+        &self,
+        mapped: &CosmicMapped,
+    ) -> Option<Rectangle<i32, Logical>> {
+        let output = self
+            .most_overlapped_output_for_element(mapped)
+            .or_else(|| self.space.outputs().next().cloned())?;
+        let output_loc = self.space.output_geometry(&output)?.loc;
+        let area = self.shrink_by_border(self.work_area(&output, Some(mapped)));
+        let size = Self::reasonable_size(
+            mapped,
+            mapped.geometry().size,
+            area.size,
+            self.config.new_window_size,
+        );
+        Some(Rectangle::from_loc_and_size(
+            output_loc
+                + area.loc
+                + Point::from(((area.size.w - size.w) / 2, (area.size.h - size.h) / 2)),
+            size,
+        ))
+    }
+
+    /// Position of `mapped` inside the work area of the output it is mostly on,
+    /// as fractions of the free space around it.
+    pub(in crate::shell) fn relative_position(
+        &self,
+        mapped: &CosmicMapped,
+    ) -> Option<Point<f64, Logical>> {
+        let output = self.most_overlapped_output_for_element(mapped)?;
+        let output_loc = self.space.output_geometry(&output)?.loc;
+        let mut geometry = self.space.element_geometry(mapped)?;
+        geometry.loc -= output_loc;
+        let area = self.work_area(&output, Some(mapped));
+
+        let fraction = |offset: i32, free: i32| {
+            if free > 0 {
+                (offset as f64 / free as f64).clamp(0.0, 1.0)
+            } else {
+                0.5
+            }
+        };
+        Some(Point::from((
+            fraction(geometry.loc.x - area.loc.x, area.size.w - geometry.size.w),
+            fraction(geometry.loc.y - area.loc.y, area.size.h - geometry.size.h),
+        )))
+    }
+
+    /// Places `mapped` on `output`, at the `relative` position returned by [`FloatingLayout::relative_position`].
+    pub(in crate::shell) fn place_relative(
+        &mut self,
+        mapped: &CosmicMapped,
+        output: &Output,
+        relative: Point<f64, Logical>,
+    ) {
+        let area = self.work_area(output, Some(mapped));
+        let mut size = mapped.geometry().size;
+        size.w = size.w.min(area.size.w);
+        size.h = size.h.min(area.size.h);
+        let loc = area.loc
+            + Point::from((
+                ((area.size.w - size.w) as f64 * relative.x).round() as i32,
+                ((area.size.h - size.h) as f64 * relative.y).round() as i32,
+            ));
+        self.place(
+            mapped.clone(),
+            output,
+            Rectangle::from_loc_and_size(loc, size),
+        );
+        self.update_home_output(mapped);
+    }
+
+    /// Moves `mapped` to the same relative position inside the work area of `target`,
+    /// it had on the output it is mostly on.
+    pub fn move_to_output(&mut self, mapped: &CosmicMapped, target: &Output) -> bool {
+        if mapped.is_maximized(true) || !self.space.outputs().any(|o| o == target) {
+            return false;
+        }
+        let Some(relative) = self.relative_position(mapped) else {
+            return false;
+        };
+        self.unsnap(mapped);
+        self.place_relative(mapped, target, relative);
+        true
+    }
+
+    /// Moves all windows, which aren't fully on `output`, onto it.
+    ///
+    /// Windows are centered in the work area of `output` and keep their size relative to
+    /// the output they were mostly on. Maximized windows are maximized on `output` instead.
+    pub fn gather_to_output(
+        &mut self,
+        output: &Output,
+        toplevel_info: &mut ToplevelInfoState<State, CosmicSurface>,
+    ) {
+        let Some(target_geo) = self.space.output_geometry(output) else {
+            return;
+        };
+        // bottom to top, so placing them on top keeps their stacking order
+        let elements = self.space.elements().cloned().collect::<Vec<_>>();
+        for mapped in elements {
+            let Some(geo) = self.space.element_geometry(&mapped) else {
+                continue;
+            };
+            if target_geo.contains_rect(geo) || mapped.is_fullscreen(true) {
+                continue;
+            }
+            let previous = self.space.outputs_for_element(&mapped);
+
+            let area = self.shrink_by_border(self.work_area(output, Some(&mapped)));
+            if mapped.is_maximized(true) {
+                self.place(
+                    mapped.clone(),
+                    output,
+                    self.work_area(output, Some(&mapped)),
+                );
+            } else {
+                let mut size = geo.size;
+                if let Some(source_area) = self
+                    .most_overlapped_output_for_element(&mapped)
+                    .filter(|source| source != output)
+                    .map(|source| self.shrink_by_border(self.work_area(&source, Some(&mapped))))
+                {
+                    size.w = (size.w as f64 * area.size.w as f64 / source_area.size.w.max(1) as f64)
+                        .round() as i32;
+                    size.h = (size.h as f64 * area.size.h as f64 / source_area.size.h.max(1) as f64)
+                        .round() as i32;
+                }
+                let size =
+                    Self::reasonable_size(&mapped, size, area.size, self.config.new_window_size);
+                let location = Point::from((
+                    area.loc.x + (area.size.w / 2) - (size.w / 2),
+                    area.loc.y + (area.size.h / 2) - (size.h / 2),
+                ));
+                self.unsnap(&mapped);
+                self.place(
+                    mapped.clone(),
+                    output,
+                    Rectangle::from_loc_and_size(location, size),
+                );
+            }
+
+            let current = self.space.outputs_for_element(&mapped);
+            for (toplevel, _) in mapped.windows() {
+                for output in previous.iter().filter(|o| !current.contains(o)) {
+                    toplevel_info.toplevel_leave_output(&toplevel, output);
+                }
+                for output in current.iter().filter(|o| !previous.contains(o)) {
+                    toplevel_info.toplevel_enter_output(&toplevel, output);
+                }
+            }
+        }
+    }
+}
+
+/// `value` moved onto a multiple of `grid`, using `round` to pick the neighbouring grid line.
+pub(crate) fn align_to_grid(value: i32, grid: i32, round: fn(f64) -> f64) -> i32 {
+    (round(value as f64 / grid as f64) * grid as f64) as i32
+}
+
+/// `geometry` moved and shrunk to fit into `zone`, unless `zone` is empty.
+pub(super) fn clamp_into(
+    mut geometry: Rectangle<i32, Logical>,
+    zone: Rectangle<i32, Logical>,
+) -> Rectangle<i32, Logical> {
+    if zone.size.w > 0 && zone.size.h > 0 {
+        geometry.size.w = geometry.size.w.min(zone.size.w);
+        geometry.size.h = geometry.size.h.min(zone.size.h);
+        geometry.loc.x = geometry
+            .loc
+            .x
+            .clamp(zone.loc.x, zone.loc.x + zone.size.w - geometry.size.w);
+        geometry.loc.y = geometry
+            .loc
+            .y
+            .clamp(zone.loc.y, zone.loc.y + zone.size.h - geometry.size.h);
+    }
+    geometry
+}
+
+/// Largest rectangle inside `area`, that doesn't overlap any of `obstacles`.
+fn largest_free_rect(
+    area: Rectangle<i32, Logical>,
+    obstacles: &[Rectangle<i32, Logical>],
+) -> Option<Rectangle<i32, Logical>> {
+    // the edges of a maximal free rectangle always lie on edges of the area or an obstacle
+    let mut xs = vec![area.loc.x, area.loc.x + area.size.w];
+    let mut ys = vec![area.loc.y, area.loc.y + area.size.h];
+    for obstacle in obstacles {
+        xs.extend([obstacle.loc.x, obstacle.loc.x + obstacle.size.w]);
+        ys.extend([obstacle.loc.y, obstacle.loc.y + obstacle.size.h]);
+    }
+    xs.retain(|x| *x >= area.loc.x && *x <= area.loc.x + area.size.w);
+    ys.retain(|y| *y >= area.loc.y && *y <= area.loc.y + area.size.h);
+    xs.sort_unstable();
+    xs.dedup();
+    ys.sort_unstable();
+    ys.dedup();
+
+    let mut best: Option<Rectangle<i32, Logical>> = None;
+    for (i, x1) in xs.iter().enumerate() {
+        for x2 in &xs[i + 1..] {
+            for (j, y1) in ys.iter().enumerate() {
+                for y2 in &ys[j + 1..] {
+                    let rect = Rectangle::from_loc_and_size((*x1, *y1), (x2 - x1, y2 - y1));
+                    let area = rect.size.w as i64 * rect.size.h as i64;
+                    if best.map_or(false, |best| {
+                        best.size.w as i64 * best.size.h as i64 >= area
+                    }) {
+                        continue;
+                    }
+                    if obstacles.iter().all(|obstacle| !obstacle.overlaps(rect)) {
+                        best = Some(rect);
+                    }
+                }
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn largest_free_rect_around_pinned_windows() {
+        let area = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+        let sidebar = Rectangle::from_loc_and_size((1520, 0), (400, 1080));
+        let top_bar = Rectangle::from_loc_and_size((0, 0), (1920, 100));
+
+        assert_eq!(largest_free_rect(area, &[]), Some(area));
+        assert_eq!(
+            largest_free_rect(area, &[sidebar]),
+            Some(Rectangle::from_loc_and_size((0, 0), (1520, 1080)))
+        );
+        assert_eq!(
+            largest_free_rect(
+                area,
+                &[
+                    top_bar,
+                    Rectangle::from_loc_and_size((1520, 100), (400, 980))
+                ]
+            ),
+            Some(Rectangle::from_loc_and_size((0, 100), (1520, 980)))
+        );
+        // obstacles reaching past the area only count with the part inside of it
+        assert_eq!(
+            largest_free_rect(
+                area,
+                &[Rectangle::from_loc_and_size((1800, 0), (400, 1080))]
+            ),
+            Some(Rectangle::from_loc_and_size((0, 0), (1800, 1080)))
+        );
+        assert_eq!(largest_free_rect(area, &[area]), None);
+    }
+
+    #[test]
+    fn clamp_into_zone() {
+        let zone = Rectangle::from_loc_and_size((0, 0), (1920, 1080));
+
+        assert_eq!(
+            clamp_into(Rectangle::from_loc_and_size((1800, -50), (400, 300)), zone),
+            Rectangle::from_loc_and_size((1520, 0), (400, 300))
+        );
+        // windows larger than the zone get shrunk to fit
+        assert_eq!(
+            clamp_into(Rectangle::from_loc_and_size((100, 100), (2500, 300)), zone),
+            Rectangle::from_loc_and_size((0, 100), (1920, 300))
+        );
+        let inside = Rectangle::from_loc_and_size((100, 100), (800, 600));
+        assert_eq!(clamp_into(inside, zone), inside);
+        // an empty zone leaves the geometry alone
+        let outside = Rectangle::from_loc_and_size((-500, 2000), (800, 600));
+        assert_eq!(clamp_into(outside, Rectangle::default()), outside);
+    }
+
+    #[test]
+    fn align_to_grid_lines() {
+        assert_eq!(align_to_grid(37, 16, f64::round), 32);
+        assert_eq!(align_to_grid(40, 16, f64::round), 48);
+        assert_eq!(align_to_grid(37, 16, f64::ceil), 48);
+        assert_eq!(align_to_grid(47, 16, f64::floor), 32);
+        assert_eq!(align_to_grid(48, 16, f64::floor), 48);
+        assert_eq!(align_to_grid(-10, 16, f64::round), -16);
+    }
+}
@@ -2173,8 +2173,9 @@ impl TilingLayout {
                                     geo.size,
                                 );
                                 if mapped.geometry() != internal_geometry {
-                                    mapped.set_geometry(internal_geometry);
-                                    if let Some(serial) = mapped.configure() {
+                                    if let Some(serial) =
+                                        mapped.configure_geometry(internal_geometry)
+                                    {
                                         configures.push((mapped.active_window(), serial));
                                     }
                                 }
@@ -3808,6 +3809,10 @@ where
             .for_each(|node_id| {
                 let data = target_tree.get(&node_id).unwrap().data();
                 let (original_geo, scaled_geo) = (data.geometry(), geometries.get(&node_id));
+                let percentage = match data {
+                    Data::Mapped { mapped, .. } if mapped.animations_disabled() => 1.0,
+                    _ => percentage,
+                };
 
                 let (old_original_geo, old_scaled_geo) =
                     if let Some(reference_tree) = reference_tree.as_ref() {
@@ -3932,6 +3937,9 @@ where
                     }
 
                     if let Some((mode, resize)) = resize_indicator.as_mut() {
+                        if resize.with_program(|internal| internal.set_size(geo.size)) {
+                            resize.force_update();
+                        }
                         let mut geo = geo.clone();
                         geo.loc -= (18, 18).into();
                         geo.size += (36, 36).into();
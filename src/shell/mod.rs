@@ -1,4 +1,7 @@
-use calloop::LoopHandle;
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     cell::RefCell,
@@ -36,7 +39,10 @@ use smithay::{
 };
 
 use crate::{
-    config::{Config, KeyModifiers, KeyPattern, OutputConfig, WorkspaceMode as ConfigMode},
+    config::{
+        Config, FloatingConfig, KeyModifiers, KeyPattern, OutputConfig, ResizeStepConfig,
+        WorkspaceMode as ConfigMode,
+    },
     utils::prelude::*,
     wayland::protocols::{
         toplevel_info::ToplevelInfoState,
@@ -63,12 +69,14 @@ use self::{
     focus::target::KeyboardFocusTarget,
     grabs::ResizeEdge,
     layout::{
-        floating::{FloatingLayout, ResizeState},
+        floating::{CloseAction, FloatingEvent, FloatingLayout, ResizeState},
         tiling::{Direction, TilingLayout},
     },
 };
 
 const ANIMATION_DURATION: Duration = Duration::from_millis(200);
+/// Time new floating windows are held back after an output change, to not place them twice.
+const OUTPUT_SETTLE_DELAY: Duration = Duration::from_millis(300);
 
 #[derive(Debug, Clone)]
 pub enum Trigger {
@@ -158,6 +166,8 @@ pub struct Shell {
     pub workspace_state: WorkspaceState<State>,
 
     gaps: (u8, u8),
+    floating: FloatingConfig,
+    resize_step: ResizeStepConfig,
     overview_mode: OverviewMode,
     resize_mode: ResizeMode,
     resize_state: Option<(
@@ -180,6 +190,7 @@ pub struct WorkspaceSet {
     idx: usize,
     tiling_enabled: bool,
     gaps: (u8, u8),
+    floating: FloatingConfig,
     pub(crate) workspaces: Vec<Workspace>,
 }
 
@@ -195,6 +206,7 @@ fn create_workspace(
     active: bool,
     tiling: bool,
     gaps: (u8, u8),
+    floating: FloatingConfig,
 ) -> Workspace {
     let workspace_handle = state.create_workspace(&group_handle).unwrap();
     if active {
@@ -204,7 +216,7 @@ fn create_workspace(
         &workspace_handle,
         [WorkspaceCapabilities::Activate].into_iter(),
     );
-    Workspace::new(workspace_handle, tiling, gaps)
+    Workspace::new(workspace_handle, tiling, gaps, floating)
 }
 
 impl WorkspaceSet {
@@ -214,12 +226,20 @@ impl WorkspaceSet {
         idx: usize,
         tiling_enabled: bool,
         gaps: (u8, u8),
+        floating: FloatingConfig,
     ) -> WorkspaceSet {
         let group_handle = state.create_workspace_group();
 
         let workspaces = match amount {
             WorkspaceAmount::Dynamic => {
-                let workspace = create_workspace(state, &group_handle, true, tiling_enabled, gaps);
+                let workspace = create_workspace(
+                    state,
+                    &group_handle,
+                    true,
+                    tiling_enabled,
+                    gaps,
+                    floating.clone(),
+                );
                 workspace_set_idx(state, 1, idx, &workspace.handle);
                 state.set_workspace_capabilities(
                     &workspace.handle,
@@ -229,8 +249,14 @@ impl WorkspaceSet {
             }
             WorkspaceAmount::Static(len) => (0..len)
                 .map(|i| {
-                    let workspace =
-                        create_workspace(state, &group_handle, i == 0, tiling_enabled, gaps);
+                    let workspace = create_workspace(
+                        state,
+                        &group_handle,
+                        i == 0,
+                        tiling_enabled,
+                        gaps,
+                        floating.clone(),
+                    );
                     workspace_set_idx(state, i + 1, idx, &workspace.handle);
                     state.set_workspace_capabilities(
                         &workspace.handle,
@@ -249,6 +275,7 @@ impl WorkspaceSet {
             idx,
             tiling_enabled,
             gaps,
+            floating,
             workspaces,
         }
     }
@@ -257,6 +284,7 @@ impl WorkspaceSet {
         &mut self,
         idx: usize,
         state: &mut WorkspaceUpdateGuard<'_, State>,
+        toplevel_info: &mut ToplevelInfoState<State, CosmicSurface>,
     ) -> Result<bool, InvalidWorkspaceIndex> {
         if idx >= self.workspaces.len() {
             return Err(InvalidWorkspaceIndex);
@@ -268,6 +296,20 @@ impl WorkspaceSet {
             state.add_workspace_state(&self.workspaces[idx].handle, WState::Active);
             self.previously_active = Some((old_active, Instant::now()));
             self.active = idx;
+
+            let old_handle = self.workspaces[old_active].handle;
+            let new_handle = self.workspaces[idx].handle;
+            for (mapped, output, geometry) in
+                self.workspaces[old_active].floating_layer.take_sticky()
+            {
+                for (toplevel, _) in mapped.windows() {
+                    toplevel_info.toplevel_leave_workspace(&toplevel, &old_handle);
+                    toplevel_info.toplevel_enter_workspace(&toplevel, &new_handle);
+                }
+                self.workspaces[idx]
+                    .floating_layer
+                    .place(mapped, &output, geometry);
+            }
             Ok(true)
         } else {
             Ok(false)
@@ -311,6 +353,7 @@ impl WorkspaceSet {
                 false,
                 self.tiling_enabled,
                 self.gaps,
+                self.floating.clone(),
             );
             workspace_set_idx(
                 &mut state,
@@ -381,7 +424,9 @@ impl WorkspaceSet {
                     }
                 }
                 last_space.tiling_layer.merge(workspace.tiling_layer);
-                last_space.floating_layer.merge(workspace.floating_layer);
+                last_space
+                    .floating_layer
+                    .merge(workspace.floating_layer, toplevel_info);
                 last_space
                     .fullscreen
                     .extend(workspace.fullscreen.into_iter());
@@ -400,6 +445,7 @@ impl WorkspaceSet {
                     false,
                     self.tiling_enabled,
                     self.gaps,
+                    self.floating.clone(),
                 );
                 workspace_set_idx(
                     &mut state,
@@ -449,11 +495,17 @@ impl WorkspaceMode {
         state: &mut WorkspaceUpdateGuard<'_, State>,
         tiling_enabled: bool,
         gaps: (u8, u8),
+        floating: FloatingConfig,
     ) -> WorkspaceMode {
         match config {
-            crate::config::WorkspaceMode::Global => {
-                WorkspaceMode::Global(WorkspaceSet::new(state, amount, 0, tiling_enabled, gaps))
-            }
+            crate::config::WorkspaceMode::Global => WorkspaceMode::Global(WorkspaceSet::new(
+                state,
+                amount,
+                0,
+                tiling_enabled,
+                gaps,
+                floating,
+            )),
             crate::config::WorkspaceMode::OutputBound => {
                 WorkspaceMode::OutputBound(HashMap::new(), amount)
             }
@@ -590,6 +642,7 @@ impl Shell {
             vec![
                 ManagementCapabilities::Close,
                 ManagementCapabilities::Activate,
+                ManagementCapabilities::Minimize,
             ],
             //|client| client.get_data::<ClientState>().map_or(false, |s| s.privileged),
             |_| true,
@@ -607,6 +660,7 @@ impl Shell {
             &mut workspace_state.update(),
             tiling_enabled,
             config.static_conf.gaps,
+            config.static_conf.floating.clone(),
         );
 
         Shell {
@@ -626,6 +680,8 @@ impl Shell {
             workspace_state,
 
             gaps: config.static_conf.gaps,
+            floating: config.static_conf.floating.clone(),
+            resize_step: config.static_conf.resize_step,
             overview_mode: OverviewMode::None,
             resize_mode: ResizeMode::None,
             resize_state: None,
@@ -639,6 +695,7 @@ impl Shell {
         }
 
         self.outputs.push(output.clone());
+        self.hold_placement();
         let mut state = self.workspace_state.update();
 
         match &mut self.workspaces {
@@ -651,6 +708,7 @@ impl Shell {
                         sets.len(),
                         self.tiling_enabled,
                         self.gaps,
+                        self.floating.clone(),
                     );
                     state.add_group_output(&set.group, &output);
                     sets.insert(output.clone(), set);
@@ -698,6 +756,7 @@ impl Shell {
             }
         }
 
+        self.hold_placement();
         let mut state = self.workspace_state.update();
         self.outputs.retain(|o| o != output);
 
@@ -751,7 +810,14 @@ impl Shell {
         };
     }
 
+    fn hold_placement(&mut self) {
+        for workspace in self.workspaces.spaces_mut() {
+            workspace.floating_layer.hold_placement(OUTPUT_SETTLE_DELAY);
+        }
+    }
+
     pub fn refresh_outputs(&mut self) {
+        self.hold_placement();
         if let WorkspaceMode::Global(set) = &mut self.workspaces {
             for workspace in &mut set.workspaces {
                 for output in self.outputs.iter() {
@@ -790,6 +856,7 @@ impl Shell {
                     0,
                     self.tiling_enabled,
                     self.gaps,
+                    self.floating.clone(),
                 );
                 for output in &self.outputs {
                     state.add_group_output(&new_set.group, output);
@@ -851,8 +918,12 @@ impl Shell {
                     );
                     workspace_set_idx(&mut state, i as u8 + 1, 0, &workspace_handle);
 
-                    let mut new_workspace =
-                        Workspace::new(workspace_handle, self.tiling_enabled, self.gaps);
+                    let mut new_workspace = Workspace::new(
+                        workspace_handle,
+                        self.tiling_enabled,
+                        self.gaps,
+                        self.floating.clone(),
+                    );
                     for output in self.outputs.iter() {
                         new_workspace.map_output(output, output.current_location());
                     }
@@ -866,7 +937,9 @@ impl Shell {
                                 .toplevel_enter_workspace(&toplevel, &new_workspace.handle);
                         }
                         new_workspace.tiling_layer.merge(workspace.tiling_layer);
-                        new_workspace.floating_layer.merge(workspace.floating_layer);
+                        new_workspace
+                            .floating_layer
+                            .merge(workspace.floating_layer, &mut self.toplevel_info_state);
                         new_workspace
                             .fullscreen
                             .extend(workspace.fullscreen.into_iter());
@@ -903,6 +976,7 @@ impl Shell {
                         i,
                         self.tiling_enabled,
                         self.gaps,
+                        self.floating.clone(),
                     );
                     state.add_group_output(&set.group, output);
                     sets.insert(output.clone(), set);
@@ -919,7 +993,7 @@ impl Shell {
                         workspace_set_idx(&mut state, i as u8 + 1, idx, &new_workspace_handle);
 
                         let mut old_tiling_layer = workspace.tiling_layer.clone();
-                        let mut new_floating_layer = FloatingLayout::new();
+                        let mut new_floating_layer = FloatingLayout::new(self.floating.clone());
                         let mut new_tiling_layer = TilingLayout::new(self.gaps);
 
                         for element in workspace.mapped() {
@@ -968,7 +1042,12 @@ impl Shell {
                                 .filter(|(key, _)| *key == output)
                                 .map(|(o, w)| (o.clone(), w.clone()))
                                 .collect(),
-                            ..Workspace::new(new_workspace_handle, true, self.gaps)
+                            ..Workspace::new(
+                                new_workspace_handle,
+                                true,
+                                self.gaps,
+                                self.floating.clone(),
+                            )
                         };
                         for toplevel in new_workspace.windows() {
                             self.toplevel_info_state
@@ -1009,12 +1088,20 @@ impl Shell {
                     ) {
                         set.workspaces[set.active].tiling_layer.cleanup_drag(output);
                     }
-                    set.activate(idx, &mut self.workspace_state.update())?
+                    set.activate(
+                        idx,
+                        &mut self.workspace_state.update(),
+                        &mut self.toplevel_info_state,
+                    )?
                 } else {
                     false
                 }
             }
-            WorkspaceMode::Global(set) => set.activate(idx, &mut self.workspace_state.update())?,
+            WorkspaceMode::Global(set) => set.activate(
+                idx,
+                &mut self.workspace_state.update(),
+                &mut self.toplevel_info_state,
+            )?,
         } {
             let output_geo = output.geometry();
             Ok(Some(
@@ -1132,6 +1219,38 @@ impl Shell {
             .find_map(|w| w.element_for_wl_surface(surface))
     }
 
+    pub fn close_request(&mut self, window: &CosmicSurface) {
+        // only floating windows can show the confirmation prompt
+        let action = self
+            .element_for_surface(window)
+            .cloned()
+            .and_then(|mapped| {
+                self.space_for_mut(&mapped)
+                    .filter(|workspace| workspace.floating_layer.mapped().any(|m| m == &mapped))
+            })
+            .map(|workspace| workspace.floating_layer.close_request(window))
+            .unwrap_or(CloseAction::Close);
+
+        if action == CloseAction::Close {
+            window.close();
+        }
+    }
+
+    pub fn minimize_request(&mut self, window: &CosmicSurface) {
+        if let Some(mapped) = self.element_for_surface(window).cloned() {
+            if let Some(workspace) = self.space_for_mut(&mapped) {
+                workspace.minimize(&mapped);
+            }
+        }
+    }
+
+    pub fn unminimize_request(&mut self, window: &CosmicSurface) -> Option<CosmicMapped> {
+        self.workspaces
+            .spaces_mut()
+            .find(|w| w.floating_layer.minimized_element(window).is_some())
+            .and_then(|workspace| workspace.restore(window))
+    }
+
     pub fn space_for(&self, mapped: &CosmicMapped) -> Option<&Workspace> {
         self.workspaces
             .spaces()
@@ -1268,11 +1387,14 @@ impl Shell {
         (self.resize_mode.clone(), self.resize_indicator.clone())
     }
 
-    pub fn refresh(&mut self) {
+    /// Returns the outputs, which need to be rendered, because windows moved onto them
+    /// and need the dmabuf feedback of the new output, or a stack on them got a new title.
+    pub fn refresh(&mut self) -> Vec<Output> {
         #[cfg(feature = "debug")]
         puffin::profile_function!();
 
         self.popups.cleanup();
+        let mut changed_outputs = Vec::new();
 
         match &mut self.workspaces {
             WorkspaceMode::OutputBound(sets, _) => {
@@ -1291,6 +1413,44 @@ impl Shell {
             ),
         }
 
+        for workspace in self.workspaces.spaces_mut() {
+            for event in workspace.floating_layer.take_events() {
+                match event {
+                    FloatingEvent::OutputChanged {
+                        window,
+                        previous,
+                        output,
+                    } => {
+                        window.set_bounds(layer_map_for_output(&output).non_exclusive_zone().size);
+                        for (toplevel, _) in window.windows() {
+                            self.toplevel_info_state
+                                .toplevel_leave_output(&toplevel, &previous);
+                            self.toplevel_info_state
+                                .toplevel_enter_output(&toplevel, &output);
+                        }
+                        if !changed_outputs.contains(&output) {
+                            changed_outputs.push(output);
+                        }
+                    }
+                    // toplevel-info clients get the new title from its refresh below,
+                    // but the tabs of a stack only redraw on their own input
+                    FloatingEvent::TitleChanged { window, .. } => {
+                        if let Some(stack) = window.stack_ref() {
+                            stack.update_tabs();
+                            if let Some(output) = workspace
+                                .floating_layer
+                                .most_overlapped_output_for_element(&window)
+                            {
+                                if !changed_outputs.contains(&output) {
+                                    changed_outputs.push(output);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
         for output in &self.outputs {
             let mut map = layer_map_for_output(output);
             map.cleanup();
@@ -1303,6 +1463,8 @@ impl Shell {
 
         self.toplevel_info_state
             .refresh(Some(&self.workspace_state));
+
+        changed_outputs
     }
 
     pub fn map_window(state: &mut State, window: &CosmicSurface, output: &Output) {
@@ -1337,8 +1499,77 @@ impl Shell {
         {
             mapped.set_debug(state.common.egui.active);
         }
+        mapped.set_animations_disabled(
+            state
+                .common
+                .config
+                .static_conf
+                .no_animations
+                .contains(&window.app_id()),
+        );
+        // states requested by the client before the window was mapped
+        let (fullscreen, maximized) = if state
+            .common
+            .config
+            .static_conf
+            .ignore_initial_state
+            .contains(&window.app_id())
+        {
+            (false, false)
+        } else {
+            (window.is_fullscreen(true), window.is_maximized(true))
+        };
+        window.set_fullscreen(false);
+        window.set_maximized(false);
+        let focused = workspace.focus_stack.get(&seat).last().cloned();
+        let mut opened_behind = false;
+
         if layout::should_be_floating(&window) || !workspace.tiling_enabled {
-            workspace.floating_layer.map(mapped.clone(), &seat, None);
+            let swallowing = window.pid(&state.common.display_handle).and_then(|pid| {
+                workspace
+                    .floating_layer
+                    .mapped()
+                    .filter(|parent| workspace.floating_layer.can_swallow(parent))
+                    .find(|parent| {
+                        parent
+                            .active_window()
+                            .pid(&state.common.display_handle)
+                            .map_or(false, |parent_pid| is_child_process(pid, parent_pid))
+                    })
+                    .cloned()
+            });
+            match swallowing {
+                Some(parent) => workspace.floating_layer.swallow(&parent, mapped.clone()),
+                // placed right away, so there is a sane geometry to restore to
+                None if fullscreen || maximized => {
+                    workspace.floating_layer.map(mapped.clone(), &seat, None)
+                }
+                None if state
+                    .common
+                    .config
+                    .static_conf
+                    .floating
+                    .open_behind
+                    .contains(&window.app_id()) =>
+                {
+                    workspace.floating_layer.map(mapped.clone(), &seat, None);
+                    if let Some(focused) = focused.as_ref() {
+                        opened_behind = workspace.floating_layer.stack_below(&mapped, focused);
+                    }
+                }
+                None => {
+                    if let Some(deadline) = workspace.floating_layer.map_new(mapped.clone(), &seat)
+                    {
+                        // wake up the loop to place the window, if nothing else happens until then
+                        let _ = state
+                            .common
+                            .event_loop_handle
+                            .insert_source(Timer::from_deadline(deadline), |_, _, _| {
+                                TimeoutAction::Drop
+                            });
+                    }
+                }
+            }
         } else {
             let focus_stack = workspace.focus_stack.get(&seat);
             workspace
@@ -1346,20 +1577,29 @@ impl Shell {
                 .map(mapped.clone(), &seat, focus_stack.iter(), None);
         }
 
-        if let CosmicSurface::X11(surface) = window {
-            if let Some(xwm) = state
-                .common
-                .xwayland_state
-                .as_mut()
-                .and_then(|state| state.xwm.as_mut())
-            {
-                if let Err(err) = xwm.raise_window(&surface) {
-                    warn!(?err, "Failed to update Xwayland stacking order.");
+        if fullscreen {
+            workspace.fullscreen_request(&window, output);
+        } else if maximized {
+            workspace.maximize_request(&window, output);
+        }
+
+        // windows opened behind the focused one neither get raised nor focused
+        if !opened_behind {
+            if let CosmicSurface::X11(surface) = window {
+                if let Some(xwm) = state
+                    .common
+                    .xwayland_state
+                    .as_mut()
+                    .and_then(|state| state.xwm.as_mut())
+                {
+                    if let Err(err) = xwm.raise_window(&surface) {
+                        warn!(?err, "Failed to update Xwayland stacking order.");
+                    }
                 }
             }
-        }
 
-        Shell::set_focus(state, Some(&KeyboardFocusTarget::from(mapped)), &seat, None);
+            Shell::set_focus(state, Some(&KeyboardFocusTarget::from(mapped)), &seat, None);
+        }
 
         let active_space = state.common.shell.active_space(output);
         for mapped in active_space.mapped() {
@@ -1420,31 +1660,84 @@ impl Shell {
         to: (&Output, Option<usize>),
         follow: bool,
         direction: Option<Direction>,
+    ) -> Result<Option<Point<i32, Logical>>, InvalidWorkspaceIndex> {
+        let from_workspace = state.common.shell.workspaces.active(from_output).1;
+        let maybe_window = from_workspace.focus_stack.get(seat).last().cloned();
+
+        let Some(mapped) = maybe_window else {
+            let (to_output, to_idx) = to;
+            let to_idx = to_idx.unwrap_or(state.common.shell.workspaces.active_num(to_output).1);
+            return match state.common.shell.workspaces.get(to_idx, to_output) {
+                Some(_) => Ok(None),
+                None => Err(InvalidWorkspaceIndex),
+            };
+        };
+        Shell::move_window(state, seat, &mapped, from_output, to, follow, direction)
+    }
+
+    /// Moves `mapped` from its workspace on `from_output` to another workspace, keeping its geometry.
+    ///
+    /// If `follow` is set, the target workspace gets activated and the window focused.
+    pub fn move_window(
+        state: &mut State,
+        seat: &Seat<State>,
+        mapped: &CosmicMapped,
+        from_output: &Output,
+        to: (&Output, Option<usize>),
+        follow: bool,
+        direction: Option<Direction>,
     ) -> Result<Option<Point<i32, Logical>>, InvalidWorkspaceIndex> {
         let (to_output, to_idx) = to;
         let to_idx = to_idx.unwrap_or(state.common.shell.workspaces.active_num(to_output).1);
-        if state
+        let Some(to_handle) = state
             .common
             .shell
             .workspaces
             .get(to_idx, to_output)
-            .is_none()
-        {
+            .map(|w| w.handle)
+        else {
             return Err(InvalidWorkspaceIndex);
-        }
+        };
 
-        if from_output == to_output
-            && to_idx == state.common.shell.workspaces.active_num(from_output).1
-        {
+        let mapped = mapped.clone();
+        let Some(from_workspace) = state
+            .common
+            .shell
+            .workspaces
+            .spaces_mut()
+            .find(|workspace| workspace.mapped().any(|m| m == &mapped))
+        else {
+            return Ok(None);
+        };
+        if from_output == to_output && from_workspace.handle == to_handle {
             return Ok(None);
         }
 
-        let from_workspace = state.common.shell.workspaces.active_mut(from_output);
-        let maybe_window = from_workspace.focus_stack.get(seat).last().cloned();
-
-        let Some(mapped) = maybe_window else {
+        if from_workspace.handle == to_handle
+            && from_output != to_output
+            && from_workspace
+                .floating_layer
+                .move_to_output(&mapped, to_output)
+        {
+            // the workspace spans both outputs, so the window just has to move inside of it
+            for (toplevel, _) in mapped.windows() {
+                state
+                    .common
+                    .shell
+                    .toplevel_info_state
+                    .toplevel_leave_output(&toplevel, from_output);
+                state
+                    .common
+                    .shell
+                    .toplevel_info_state
+                    .toplevel_enter_output(&toplevel, to_output);
+            }
+            if follow {
+                seat.set_active_output(to_output);
+            }
             return Ok(None);
-        };
+        }
+        let relative = from_workspace.floating_layer.relative_position(&mapped);
         let Some(window_state) = from_workspace.unmap(&mapped) else {
             return Ok(None);
         };
@@ -1484,6 +1777,11 @@ impl Shell {
         let focus_stack = to_workspace.focus_stack.get(&seat);
         if window_state == ManagedState::Floating {
             to_workspace.floating_layer.map(mapped.clone(), &seat, None);
+            if let Some(relative) = relative.filter(|_| !mapped.is_maximized(true)) {
+                to_workspace
+                    .floating_layer
+                    .place_relative(&mapped, to_output, relative);
+            }
         } else {
             to_workspace
                 .tiling_layer
@@ -1556,6 +1854,12 @@ impl Shell {
                         &output,
                         start_data,
                         state.common.config.static_conf.active_hint,
+                        state
+                            .common
+                            .config
+                            .static_conf
+                            .floating
+                            .magnetic_edge_threshold,
                     ) {
                         let handle = workspace.handle;
                         state
@@ -1619,13 +1923,11 @@ impl Shell {
         };
 
         if let Some(workspace) = self.workspaces.get_mut(idx, &output) {
-            let amount = (self
-                .resize_state
-                .take()
-                .map(|(_, _, _, amount, _, _)| amount)
-                .unwrap_or(10)
-                + 2)
-            .min(20);
+            let amount = self.resize_step.next(
+                self.resize_state
+                    .take()
+                    .map(|(_, _, _, amount, _, _)| amount),
+            );
             if workspace.resize(&focused, direction, edge, amount) {
                 self.resize_state = Some((focused, direction, edge, amount, idx, output));
             }
@@ -1667,6 +1969,28 @@ fn workspace_set_idx<'a>(
     state.set_workspace_coordinates(&handle, [Some(idx as u32), Some(output_pos as u32), None]);
 }
 
+/// Whether `pid` was (indirectly) started by `ancestor`.
+fn is_child_process(mut pid: i32, ancestor: i32) -> bool {
+    while pid > 1 {
+        let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid)) else {
+            return false;
+        };
+        // the process name may contain spaces or parentheses, so skip past the last `)`
+        let Some(ppid) = stat
+            .rsplit_once(')')
+            .and_then(|(_, rest)| rest.split_whitespace().nth(1))
+            .and_then(|ppid| ppid.parse::<i32>().ok())
+        else {
+            return false;
+        };
+        if ppid == ancestor {
+            return true;
+        }
+        pid = ppid;
+    }
+    false
+}
+
 pub fn check_grab_preconditions(
     seat: &Seat<State>,
     surface: &WlSurface,
@@ -3,6 +3,7 @@ use crate::{
         element::{AsGlowFrame, AsGlowRenderer},
         BackdropShader, GlMultiError, GlMultiFrame, GlMultiRenderer,
     },
+    config::FloatingConfig,
     shell::{
         grabs::MoveGrab,
         layout::{
@@ -66,6 +67,8 @@ pub struct Workspace {
     pub floating_layer: FloatingLayout,
     pub tiling_enabled: bool,
     pub fullscreen: HashMap<Output, CosmicSurface>,
+    /// Maximized windows, which take over their output once their animation ends
+    pending_maximize: Vec<(CosmicSurface, Output)>,
     pub handle: WorkspaceHandle,
     pub focus_stack: FocusStacks,
     pub pending_buffers: Vec<(ScreencopySession, BufferParams)>,
@@ -83,12 +86,18 @@ pub enum ManagedState {
 }
 
 impl Workspace {
-    pub fn new(handle: WorkspaceHandle, tiling_enabled: bool, gaps: (u8, u8)) -> Workspace {
+    pub fn new(
+        handle: WorkspaceHandle,
+        tiling_enabled: bool,
+        gaps: (u8, u8),
+        floating: FloatingConfig,
+    ) -> Workspace {
         Workspace {
             tiling_layer: TilingLayout::new(gaps),
-            floating_layer: FloatingLayout::new(),
+            floating_layer: FloatingLayout::new(floating),
             tiling_enabled,
             fullscreen: HashMap::new(),
+            pending_maximize: Vec::new(),
             handle,
             focus_stack: FocusStacks::default(),
             pending_buffers: Vec::new(),
@@ -102,15 +111,26 @@ impl Workspace {
         puffin::profile_function!();
 
         self.fullscreen.retain(|_, w| w.alive());
+        self.pending_maximize.retain(|(w, _)| w.alive());
         self.floating_layer.refresh();
         self.tiling_layer.refresh();
     }
 
     pub fn animations_going(&self) -> bool {
-        self.tiling_layer.animations_going()
+        self.tiling_layer.animations_going() || self.floating_layer.animations_going()
     }
 
     pub fn update_animations(&mut self) -> HashMap<ClientId, Client> {
+        self.floating_layer.update_animations();
+        let (finished, pending) = std::mem::take(&mut self.pending_maximize)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(window, _)| !self.floating_layer.is_animating(window));
+        self.pending_maximize = pending;
+        for (window, output) in finished {
+            if window.is_maximized(true) && !self.fullscreen.contains_key(&output) {
+                self.set_fullscreen(&window, &output);
+            }
+        }
         self.tiling_layer.update_animation_state()
     }
 
@@ -167,6 +187,20 @@ impl Workspace {
         }
     }
 
+    /// Merges the floating `dragged` into `target`, see [`FloatingLayout::stack_with`].
+    pub fn stack_with(
+        &mut self,
+        target: &CosmicMapped,
+        dragged: &CosmicMapped,
+    ) -> Option<CosmicMapped> {
+        let stack = self.floating_layer.stack_with(target, dragged)?;
+        self.focus_stack
+            .0
+            .values_mut()
+            .for_each(|set| set.retain(|m| m != target && m != dragged));
+        Some(stack)
+    }
+
     pub fn element_for_surface(&self, surface: &CosmicSurface) -> Option<&CosmicMapped> {
         self.floating_layer
             .mapped()
@@ -207,7 +241,6 @@ impl Workspace {
         overview: OverviewMode,
     ) -> Option<(PointerFocusTarget, Point<i32, Logical>)> {
         self.floating_layer
-            .space
             .element_under(location)
             .map(|(mapped, p)| (mapped.clone().into(), p))
             .or_else(|| self.tiling_layer.element_under(location, overview))
@@ -243,12 +276,36 @@ impl Workspace {
 
         window.set_fullscreen(false);
         window.set_maximized(true);
+        if self.floating_layer.maximize_around_pinned(window, output) {
+            return;
+        }
+        if self.floating_layer.maximize_animated(window, output) {
+            self.pending_maximize.push((window.clone(), output.clone()));
+            return;
+        }
         self.set_fullscreen(window, output)
     }
     pub fn unmaximize_request(&mut self, window: &CosmicSurface) -> Option<Size<i32, Logical>> {
         if self.fullscreen.values().any(|w| w == window) {
             self.unfullscreen_request(window);
             self.floating_layer.unmaximize_request(window)
+        } else if let Some(idx) = self.pending_maximize.iter().position(|(w, _)| w == window) {
+            // still animating towards the maximized geometry
+            self.pending_maximize.remove(idx);
+            window.set_maximized(false);
+            let size = self.floating_layer.unmaximize_request(window);
+            window.send_configure();
+            size
+        } else if self.floating_layer.is_maximized_around_pinned(window) {
+            // maximized around pinned windows, without taking over the output
+            window.set_maximized(false);
+            let size = self.floating_layer.unmaximize_request(window);
+            window.send_configure();
+            size
+        } else if self.floating_layer.is_maximized_axis(window) {
+            let size = self.floating_layer.unmaximize_request(window);
+            window.send_configure();
+            size
         } else {
             None
         }
@@ -278,8 +335,13 @@ impl Workspace {
                     mapped.set_active(window);
                 }
 
-                window.set_geometry(output.geometry());
-                window.send_configure();
+                let mut geometry = output.geometry();
+                if window.is_maximized(true) {
+                    let maximized = self.floating_layer.maximized_geometry(output);
+                    geometry.loc += maximized.loc;
+                    geometry.size = maximized.size;
+                }
+                window.configure_geometry(geometry);
                 self.fullscreen.insert(output.clone(), window.clone());
             }
             None => {
@@ -302,18 +364,79 @@ impl Workspace {
         }
     }
 
-    pub fn maximize_toggle(&mut self, window: &CosmicSurface, output: &Output) {
-        if self.fullscreen.contains_key(output) {
+    /// Maximizes `window`, or restores the floating geometry it had right before being maximized.
+    pub fn toggle_maximize(&mut self, window: &CosmicSurface, output: &Output) {
+        if window.is_maximized(true) {
             self.unmaximize_request(window);
         } else {
             self.maximize_request(window, output);
         }
     }
 
+    /// Restores the geometry `mapped` had before it was last moved, resized, placed or maximized.
+    pub fn undo_geometry(&mut self, mapped: &CosmicMapped) -> bool {
+        if mapped.is_maximized(true) {
+            // unmaximizing restores the geometry recorded by the maximize
+            return self.unmaximize_request(&mapped.active_window()).is_some();
+        }
+        self.floating_layer.undo_geometry(mapped)
+    }
+
+    /// Minimizes the floating `mapped`, keeping it maximized, if it was, for when it gets restored.
+    pub fn minimize(&mut self, mapped: &CosmicMapped) -> bool {
+        if !self.floating_layer.mapped().any(|m| m == mapped) {
+            return false;
+        }
+        self.fullscreen
+            .retain(|_, w| !mapped.windows().any(|(s, _)| &s == w));
+        self.floating_layer.minimize(mapped)
+    }
+
+    /// Restores the minimized window containing `window`, maximizing it again if it was maximized.
+    pub fn restore(&mut self, window: &CosmicSurface) -> Option<CosmicMapped> {
+        let mapped = self.floating_layer.minimized_element(window)?.clone();
+        let (output, maximized) = self.floating_layer.restore(&mapped)?;
+        let active = mapped.active_window();
+        if maximized && !self.floating_layer.is_maximized_around_pinned(&active) {
+            if self.fullscreen.contains_key(&output) {
+                active.set_maximized(false);
+                self.floating_layer.unmaximize_request(&active);
+                active.send_configure();
+            } else {
+                self.set_fullscreen(&active, &output);
+            }
+        }
+        Some(mapped)
+    }
+
     pub fn get_fullscreen(&self, output: &Output) -> Option<&CosmicSurface> {
         self.fullscreen.get(output).filter(|w| w.alive())
     }
 
+    /// Window on `output`, whose buffers may be scanned out directly, because it covers the output on its own.
+    pub fn scanout_candidate(&self, output: &Output) -> Option<CosmicSurface> {
+        match self.get_fullscreen(output) {
+            Some(window) => Some(window.clone()),
+            None => self
+                .floating_layer
+                .scanout_candidate(output)
+                .map(|mapped| mapped.active_window()),
+        }
+    }
+
+    /// Output-local geometry of the fullscreen or maximized window on `output`.
+    ///
+    /// Maximized windows stay inside the output's safe area, fullscreen ones cover everything.
+    pub fn fullscreen_geometry(&self, output: &Output) -> Option<Rectangle<i32, Logical>> {
+        self.get_fullscreen(output).map(|window| {
+            if window.is_maximized(true) {
+                self.floating_layer.maximized_geometry(output)
+            } else {
+                Rectangle::from_loc_and_size((0, 0), output.geometry().size)
+            }
+        })
+    }
+
     pub fn resize_request(
         &mut self,
         mapped: &CosmicMapped,
@@ -365,12 +488,13 @@ impl Workspace {
         output: &Output,
         start_data: PointerGrabStartData<State>,
         indicator_thickness: u8,
+        snap_threshold: u32,
     ) -> Option<MoveGrab> {
         let pointer = seat.get_pointer().unwrap();
         let pos = pointer.current_location();
 
         let mapped = self.element_for_surface(&window)?.clone();
-        let mut initial_window_location = self.element_geometry(&mapped).unwrap().loc;
+        let mut initial_window_location = self.element_geometry(&mapped)?.loc;
 
         if mapped.is_fullscreen(true) || mapped.is_maximized(true) {
             // If surface is maximized then unmaximize it
@@ -381,6 +505,12 @@ impl Workspace {
                 .map(|size| (pos.x - (size.w as f64 * ratio), pos.y).into())
                 .unwrap_or_else(|| pos)
                 .to_i32_round();
+        } else if let Some(size) = self.floating_layer.snapped_size(&mapped) {
+            // restore the size from before snapping, keeping the pointer at the same relative position
+            let geometry = mapped.geometry();
+            let ratio = (pos.x - initial_window_location.x as f64) / geometry.size.w.max(1) as f64;
+            initial_window_location.x = (pos.x - size.w as f64 * ratio).round() as i32;
+            mapped.configure_geometry(Rectangle::from_loc_and_size(initial_window_location, size));
         }
 
         let was_floating = self.floating_layer.unmap(&mapped);
@@ -394,6 +524,7 @@ impl Workspace {
             pos,
             initial_window_location,
             indicator_thickness,
+            snap_threshold,
             was_tiled.is_some(),
         ))
     }
@@ -454,9 +585,10 @@ impl Workspace {
         self.floating_layer.space.outputs()
     }
 
+    /// All windows of the workspace, including minimized ones
     pub fn windows(&self) -> impl Iterator<Item = CosmicSurface> + '_ {
         self.floating_layer
-            .windows()
+            .windows_with_minimized(true)
             .chain(self.tiling_layer.windows().map(|(_, w, _)| w))
     }
 
@@ -540,7 +672,10 @@ impl Workspace {
             >(
                 fullscreen,
                 renderer,
-                (0, 0).into(),
+                self.fullscreen_geometry(output)
+                    .map(|geo| geo.loc)
+                    .unwrap_or_default()
+                    .to_physical_precise_round(output_scale),
                 output_scale.into(),
                 1.0,
             ));
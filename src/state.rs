@@ -1,8 +1,10 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
+#[cfg(feature = "debug")]
+use crate::wayland::handlers::dmabuf::DmabufStats;
 use crate::{
     backend::{
-        kms::{source_node_for_surface, KmsState},
+        kms::{source_node_for_surface, KmsState, ScanoutSupport},
         winit::WinitState,
         x11::X11State,
     },
@@ -29,6 +31,7 @@ use rust_embed::RustEmbed;
 use smithay::utils::Rectangle;
 use smithay::{
     backend::{
+        allocator::{Buffer, Format},
         drm::DrmNode,
         renderer::{
             element::{
@@ -36,6 +39,7 @@ use smithay::{
                 RenderElementStates,
             },
             glow::GlowRenderer,
+            utils::with_renderer_surface_state,
         },
     },
     desktop::utils::{
@@ -51,7 +55,7 @@ use smithay::{
         wayland_protocols_misc::server_decoration::server::org_kde_kwin_server_decoration_manager::Mode,
         wayland_server::{
             backend::{ClientData, ClientId, DisconnectReason},
-            protocol::wl_shm,
+            protocol::{wl_shm, wl_surface::WlSurface},
             Display, DisplayHandle,
         },
     },
@@ -59,7 +63,7 @@ use smithay::{
     wayland::{
         compositor::{CompositorClientState, CompositorState},
         data_device::DataDeviceState,
-        dmabuf::{DmabufFeedback, DmabufState},
+        dmabuf::{get_dmabuf, DmabufFeedback, DmabufState},
         fractional_scale::{with_fractional_scale, FractionalScaleManagerState},
         keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitState,
         output::OutputManagerState,
@@ -144,6 +148,8 @@ pub struct Common {
     pub compositor_state: CompositorState,
     pub data_device_state: DataDeviceState,
     pub dmabuf_state: DmabufState,
+    #[cfg(feature = "debug")]
+    pub dmabuf_stats: DmabufStats,
     pub fractional_scale_state: FractionalScaleManagerState,
     pub keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState,
     pub output_state: OutputManagerState,
@@ -238,7 +244,12 @@ impl BackendData {
                 .filter(|x| *x != output.current_scale().fractional_scale());
             let location =
                 Some(final_config.position.into()).filter(|x| *x != output.current_location());
+            let resized = mode.is_some() || transform.is_some() || scale.is_some();
             output.change_current_state(mode, transform, scale.map(Scale::Fractional), location);
+            if resized {
+                // the non-exclusive zone would otherwise keep the old size, e.g. unrotated
+                smithay::desktop::layer_map_for_output(output).arrange();
+            }
         }
 
         result
@@ -332,6 +343,8 @@ impl State {
                 compositor_state,
                 data_device_state,
                 dmabuf_state,
+                #[cfg(feature = "debug")]
+                dmabuf_stats: DmabufStats::default(),
                 fractional_scale_state,
                 screencopy_state,
                 shm_state,
@@ -430,6 +443,7 @@ impl Common {
         output: &Output,
         render_element_states: &RenderElementStates,
         mut dmabuf_feedback: impl FnMut(DrmNode) -> Option<SurfaceDmabufFeedback>,
+        scanout_support: impl Fn(Format) -> ScanoutSupport,
     ) {
         let time = self.clock.now();
         let throttle = Some(Duration::from_secs(1));
@@ -489,6 +503,11 @@ impl Common {
         }
 
         let active = self.shell.active_space(output);
+        let focused = self
+            .last_active_seat
+            .as_ref()
+            .and_then(|seat| active.focus_stack.get(seat).last().cloned());
+        let scanout_candidate = active.scanout_candidate(output);
         active.mapped().for_each(|mapped| {
             let outputs_for_element: Vec<_> = active.outputs_for_element(mapped).collect();
             if outputs_for_element.contains(&output) {
@@ -519,7 +538,12 @@ impl Common {
                         });
                     }
                 });
-                window.send_frame(output, time, throttle, surface_primary_scanout_output);
+                let frame_throttle = active
+                    .floating_layer
+                    .frame_throttle(mapped, focused.as_ref());
+                if !mapped.skip_frame(time.into(), frame_interval(output, frame_throttle)) {
+                    window.send_frame(output, time, throttle, surface_primary_scanout_output);
+                }
                 if let Some(feedback) = window
                     .wl_surface()
                     .and_then(|wl_surface| {
@@ -527,6 +551,23 @@ impl Common {
                     })
                     .and_then(|source| dmabuf_feedback(source))
                 {
+                    // offer formats suitable for the primary plane to a window covering the output,
+                    // whose buffers can't be put on it yet, so it can reallocate them
+                    let needs_scanout_formats = scanout_candidate.as_ref() == Some(&window)
+                        && window
+                            .wl_surface()
+                            .and_then(|surface| dmabuf_format(&surface))
+                            .map_or(false, |format| {
+                                scanout_support(format) != ScanoutSupport::Primary
+                            });
+                    let feedback = if needs_scanout_formats {
+                        SurfaceDmabufFeedback {
+                            render_feedback: feedback.scanout_feedback.clone(),
+                            scanout_feedback: feedback.scanout_feedback,
+                        }
+                    } else {
+                        feedback
+                    };
                     window.send_dmabuf_feedback(
                         output,
                         &feedback,
@@ -910,3 +951,28 @@ pub fn avg_fps<'a>(iter: impl Iterator<Item = &'a Duration>) -> f64 {
     let sum_secs = iter.map(|d| d.as_secs_f64()).sum::<f64>();
     1.0 / (sum_secs / Fps::WINDOW_SIZE as f64)
 }
+
+/// Minimum time between frame callbacks of a window on `output`, only passed on every `throttle`-th frame.
+///
+/// Half a frame of slack keeps jitter in the frame timing from holding callbacks back for a whole extra frame.
+fn frame_interval(output: &Output, throttle: u32) -> Duration {
+    if throttle <= 1 {
+        return Duration::ZERO;
+    }
+    let refresh = output
+        .current_mode()
+        .map(|mode| mode.refresh)
+        .filter(|refresh| *refresh > 0)
+        .unwrap_or(60_000);
+    Duration::from_secs_f64((throttle as f64 - 0.5) * 1000.0 / refresh as f64)
+}
+
+/// Format of the dmabuf currently attached to `surface`, if it is backed by one.
+fn dmabuf_format(surface: &WlSurface) -> Option<Format> {
+    with_renderer_surface_state(surface, |state| {
+        state
+            .buffer()
+            .and_then(|buffer| get_dmabuf(buffer).ok())
+            .map(|dmabuf| dmabuf.format())
+    })
+}
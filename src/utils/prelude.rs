@@ -2,6 +2,7 @@ use std::{cell::RefCell, sync::Mutex, time::Duration};
 
 use crate::{
     backend::render::cursor::{CursorShape, CursorState},
+    config::OutputConfig,
     input::{ActiveOutput, SeatId},
 };
 use smithay::{
@@ -21,6 +22,8 @@ pub use crate::wayland::handlers::xdg_shell::popup::update_reactive_popups;
 
 pub trait OutputExt {
     fn geometry(&self) -> Rectangle<i32, Logical>;
+    /// Part of the output not covered by the configured safe-area insets, in output-local coordinates
+    fn safe_area(&self) -> Rectangle<i32, Logical>;
 }
 
 impl OutputExt for Output {
@@ -37,6 +40,24 @@ impl OutputExt for Output {
                 .to_i32_round()
         })
     }
+
+    fn safe_area(&self) -> Rectangle<i32, Logical> {
+        let size = self.geometry().size;
+        let full = Rectangle::from_loc_and_size((0, 0), size);
+        let Some((top, right, bottom, left)) = self
+            .user_data()
+            .get::<RefCell<OutputConfig>>()
+            .map(|conf| conf.borrow().safe_area)
+        else {
+            return full;
+        };
+        let (top, right, bottom, left) = (top.max(0), right.max(0), bottom.max(0), left.max(0));
+        if size.w - left - right <= 0 || size.h - top - bottom <= 0 {
+            // insets larger than the output, ignore them
+            return full;
+        }
+        Rectangle::from_loc_and_size((left, top), (size.w - left - right, size.h - top - bottom))
+    }
 }
 
 pub trait SeatExt {
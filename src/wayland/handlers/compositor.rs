@@ -143,6 +143,9 @@ impl CompositorHandler for State {
                     })
             });
             if let Some(dmabuf) = maybe_dmabuf {
+                if let BackendData::Kms(kms_state) = &state.backend {
+                    kms_state.record_fallback_node(surface, &dmabuf);
+                }
                 if let Ok((blocker, source)) = dmabuf.generate_blocker(Interest::READ) {
                     let client = surface.client().unwrap();
                     let res =
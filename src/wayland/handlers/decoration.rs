@@ -109,6 +109,23 @@ impl State {
             window.toplevel().send_configure();
         }
     }
+
+    /// Keeps the content of a floating window in place, once its client acked a configure with `mode`.
+    pub fn decoration_acked(&mut self, surface: &WlSurface, mode: Option<XdgMode>) {
+        let Some(mapped) = self.common.shell.element_for_wl_surface(surface).cloned() else {
+            return;
+        };
+        let decorated = mode == Some(XdgMode::ServerSide);
+        // the current state only changes with the commit following the ack
+        if !mapped.is_window() || !mapped.active_window().is_decorated(false) == decorated {
+            return;
+        }
+        if let Some(workspace) = self.common.shell.space_for_mut(&mapped) {
+            workspace
+                .floating_layer
+                .decoration_acked(&mapped, decorated);
+        }
+    }
 }
 
 impl XdgDecorationHandler for State {
@@ -1,11 +1,68 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
-use crate::state::{BackendData, State};
+use crate::{
+    backend::render::max_texture_size,
+    state::{BackendData, State},
+};
 use smithay::{
-    backend::{allocator::dmabuf::Dmabuf, renderer::ImportDma},
+    backend::{
+        allocator::{dmabuf::Dmabuf, Buffer, Format},
+        renderer::ImportDma,
+    },
     delegate_dmabuf,
     wayland::dmabuf::{DmabufGlobal, DmabufHandler, DmabufState, ImportError},
 };
+use tracing::warn;
+
+/// Successful and failed dmabuf imports of one backend
+#[cfg(feature = "debug")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ImportCounter {
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+#[cfg(feature = "debug")]
+impl ImportCounter {
+    /// Share of failed imports, 0 if nothing was imported yet
+    pub fn failure_ratio(&self) -> f64 {
+        let total = self.succeeded + self.failed;
+        if total == 0 {
+            0.0
+        } else {
+            self.failed as f64 / total as f64
+        }
+    }
+}
+
+/// Dmabuf import statistics since startup, for debugging clients whose buffers fail to import
+#[cfg(feature = "debug")]
+#[derive(Debug, Default)]
+pub struct DmabufStats {
+    pub kms: ImportCounter,
+    pub winit: ImportCounter,
+    pub x11: ImportCounter,
+    /// Format and modifier of the most recent buffer that failed to import
+    pub last_failure: Option<Format>,
+}
+
+#[cfg(feature = "debug")]
+impl DmabufStats {
+    fn record(&mut self, backend: &BackendData, dmabuf: &Dmabuf, success: bool) {
+        let counter = match backend {
+            BackendData::Kms(_) => &mut self.kms,
+            BackendData::Winit(_) => &mut self.winit,
+            BackendData::X11(_) => &mut self.x11,
+            BackendData::Unset => return,
+        };
+        if success {
+            counter.succeeded += 1;
+        } else {
+            counter.failed += 1;
+            self.last_failure = Some(dmabuf.format());
+        }
+    }
+}
 
 impl DmabufHandler for State {
     fn dmabuf_state(&mut self) -> &mut DmabufState {
@@ -17,23 +74,107 @@ impl DmabufHandler for State {
         global: &DmabufGlobal,
         dmabuf: Dmabuf,
     ) -> Result<(), ImportError> {
-        match &mut self.backend {
+        let result = match &mut self.backend {
+            // logs failures with the node the import was attempted on
             BackendData::Kms(ref mut state) => state
-                .dmabuf_imported(global, dmabuf)
-                .map_err(|_| ImportError::Failed),
-            BackendData::Winit(ref mut state) => state
-                .backend
-                .renderer()
-                .import_dmabuf(&dmabuf, None)
-                .map(|_| ())
-                .map_err(|_| ImportError::Failed),
-            BackendData::X11(ref mut state) => state
-                .renderer
-                .import_dmabuf(&dmabuf, None)
-                .map(|_| ())
+                .dmabuf_imported(
+                    global,
+                    dmabuf.clone(),
+                    &self.common.config.static_conf.dmabuf,
+                )
                 .map_err(|_| ImportError::Failed),
+            BackendData::Winit(ref mut state) => {
+                let renderer = state.backend.renderer();
+                match max_texture_size(renderer) {
+                    Ok(max_size) if !fits_texture_size(&dmabuf, max_size) => {
+                        Err(ImportError::Failed)
+                    }
+                    _ => renderer
+                        .import_dmabuf(&dmabuf, None)
+                        .map(|_| ())
+                        .map_err(|err| {
+                            warn!(
+                                ?err,
+                                buffer = %describe_dmabuf(&dmabuf),
+                                supported = %supported_modifiers(&dmabuf, renderer.dmabuf_formats()),
+                                "Failed to import dmabuf."
+                            );
+                            ImportError::Failed
+                        }),
+                }
+            }
+            BackendData::X11(ref mut state) => {
+                let renderer = &mut state.renderer;
+                match max_texture_size(renderer) {
+                    Ok(max_size) if !fits_texture_size(&dmabuf, max_size) => {
+                        Err(ImportError::Failed)
+                    }
+                    _ => renderer
+                        .import_dmabuf(&dmabuf, None)
+                        .map(|_| ())
+                        .map_err(|err| {
+                            warn!(
+                                ?err,
+                                buffer = %describe_dmabuf(&dmabuf),
+                                supported = %supported_modifiers(&dmabuf, renderer.dmabuf_formats()),
+                                "Failed to import dmabuf."
+                            );
+                            ImportError::Failed
+                        }),
+                }
+            }
             _ => unreachable!("No backend set when importing dmabuf"),
-        }
+        };
+        #[cfg(feature = "debug")]
+        self.common
+            .dmabuf_stats
+            .record(&self.backend, &dmabuf, result.is_ok());
+        result
+    }
+}
+
+/// Whether `dmabuf` fits into a texture of at most `max_size` in both dimensions, logging it otherwise.
+///
+/// Larger buffers are rejected before trying to import them, as some drivers don't handle that gracefully.
+pub fn fits_texture_size(dmabuf: &Dmabuf, max_size: i32) -> bool {
+    let size = dmabuf.size();
+    if size.w > max_size || size.h > max_size {
+        warn!(
+            buffer = %describe_dmabuf(dmabuf),
+            max_size,
+            "Rejecting dmabuf larger than the maximum texture size."
+        );
+        return false;
+    }
+    true
+}
+
+/// Format, modifier and size of `dmabuf`, for logging failed imports.
+pub fn describe_dmabuf(dmabuf: &Dmabuf) -> String {
+    let format = dmabuf.format();
+    let size = dmabuf.size();
+    format!(
+        "{:?} with modifier {:?} ({}x{}, {} planes)",
+        format.code,
+        format.modifier,
+        size.w,
+        size.h,
+        dmabuf.num_planes()
+    )
+}
+
+/// Modifiers out of `formats` supported for the fourcc code of `dmabuf`, for logging failed imports.
+pub fn supported_modifiers(dmabuf: &Dmabuf, formats: impl IntoIterator<Item = Format>) -> String {
+    let code = dmabuf.format().code;
+    let modifiers = formats
+        .into_iter()
+        .filter(|format| format.code == code)
+        .map(|format| format!("{:?}", format.modifier))
+        .collect::<Vec<_>>();
+    if modifiers.is_empty() {
+        format!("none, {:?} is not supported at all", code)
+    } else {
+        modifiers.join(", ")
     }
 }
 
@@ -42,11 +42,17 @@ impl ToplevelManagementHandler for State {
                 .find(|(_, w)| w.windows().any(|w| &w == window));
             if let Some((idx, workspace)) = maybe {
                 let seat = seat.unwrap_or(self.common.last_active_seat().clone());
-                let mapped = workspace
+                let mapped = match workspace
                     .mapped()
                     .find(|m| m.windows().any(|(w, _)| &w == window))
-                    .unwrap()
-                    .clone();
+                    .cloned()
+                {
+                    Some(mapped) => mapped,
+                    None => match self.common.shell.unminimize_request(window) {
+                        Some(mapped) => mapped,
+                        None => return,
+                    },
+                };
 
                 let _ = self.common.shell.activate(&output, idx as usize); // TODO: Move pointer?
                 mapped.focus_window(window);
@@ -57,7 +63,15 @@ impl ToplevelManagementHandler for State {
     }
 
     fn close(&mut self, _dh: &DisplayHandle, window: &<Self as ToplevelInfoHandler>::Window) {
-        window.close();
+        self.common.shell.close_request(window);
+    }
+
+    fn minimize(&mut self, _dh: &DisplayHandle, window: &<Self as ToplevelInfoHandler>::Window) {
+        self.common.shell.minimize_request(window);
+    }
+
+    fn unminimize(&mut self, _dh: &DisplayHandle, window: &<Self as ToplevelInfoHandler>::Window) {
+        self.common.shell.unminimize_request(window);
     }
 }
 
@@ -11,13 +11,14 @@ use smithay::{
     output::Output,
     reexports::{
         wayland_protocols::xdg::shell::server::xdg_toplevel,
-        wayland_server::protocol::{wl_output::WlOutput, wl_seat::WlSeat},
+        wayland_server::protocol::{wl_output::WlOutput, wl_seat::WlSeat, wl_surface::WlSurface},
     },
     utils::Serial,
     wayland::{
         seat::WaylandFocus,
         shell::xdg::{
-            PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState,
+            Configure, PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler,
+            XdgShellState,
         },
     },
 };
@@ -42,6 +43,12 @@ impl XdgShellHandler for State {
         // We will position the window after the first commit, when we know its size hints
     }
 
+    fn ack_configure(&mut self, surface: WlSurface, configure: Configure) {
+        if let Configure::Toplevel(configure) = configure {
+            self.decoration_acked(&surface, configure.state.decoration_mode);
+        }
+    }
+
     fn new_popup(&mut self, surface: PopupSurface, positioner: PositionerState) {
         surface.with_pending_state(|state| {
             state.geometry = positioner.get_geometry();
@@ -166,6 +173,15 @@ impl XdgShellHandler for State {
                     .unwrap();
                 workspace.maximize_request(&window, &output)
             }
+        } else if let Some((window, _)) = self
+            .common
+            .shell
+            .pending_windows
+            .iter()
+            .find(|(w, _)| w.wl_surface().as_ref() == Some(surface.wl_surface()))
+        {
+            // applied once the window gets mapped
+            window.set_maximized(true);
         }
     }
 
@@ -183,6 +199,28 @@ impl XdgShellHandler for State {
                     .unwrap();
                 workspace.unmaximize_request(&window);
             }
+        } else if let Some((window, _)) = self
+            .common
+            .shell
+            .pending_windows
+            .iter()
+            .find(|(w, _)| w.wl_surface().as_ref() == Some(surface.wl_surface()))
+        {
+            // applied once the window gets mapped
+            window.set_maximized(false);
+        }
+    }
+
+    fn minimize_request(&mut self, surface: ToplevelSurface) {
+        if let Some(mapped) = self
+            .common
+            .shell
+            .element_for_wl_surface(surface.wl_surface())
+            .cloned()
+        {
+            if let Some(workspace) = self.common.shell.space_for_mut(&mapped) {
+                workspace.minimize(&mapped);
+            }
         }
     }
 
@@ -208,6 +246,15 @@ impl XdgShellHandler for State {
                     .unwrap();
                 workspace.fullscreen_request(&window, &output)
             }
+        } else if let Some((window, _)) = self
+            .common
+            .shell
+            .pending_windows
+            .iter()
+            .find(|(w, _)| w.wl_surface().as_ref() == Some(surface.wl_surface()))
+        {
+            // applied once the window gets mapped
+            window.set_fullscreen(true);
         }
     }
 
@@ -225,6 +272,15 @@ impl XdgShellHandler for State {
                     .unwrap();
                 workspace.unfullscreen_request(&window)
             }
+        } else if let Some((window, _)) = self
+            .common
+            .shell
+            .pending_windows
+            .iter()
+            .find(|(w, _)| w.wl_surface().as_ref() == Some(surface.wl_surface()))
+        {
+            // applied once the window gets mapped
+            window.set_fullscreen(false);
         }
     }
 
@@ -398,6 +398,16 @@ impl XwmHandler for Data {
                 let (window, _) = mapped.windows().find(|(w, _)| w == &surface).unwrap();
                 workspace.maximize_request(&window, &output)
             }
+        } else if let Some((window, _)) = self
+            .state
+            .common
+            .shell
+            .pending_windows
+            .iter()
+            .find(|(w, _)| w == &surface)
+        {
+            // applied once the window gets mapped
+            window.set_maximized(true);
         }
     }
 
@@ -414,6 +424,16 @@ impl XwmHandler for Data {
                 let (window, _) = mapped.windows().find(|(w, _)| w == &surface).unwrap();
                 workspace.unmaximize_request(&window);
             }
+        } else if let Some((window, _)) = self
+            .state
+            .common
+            .shell
+            .pending_windows
+            .iter()
+            .find(|(w, _)| w == &surface)
+        {
+            // applied once the window gets mapped
+            window.set_maximized(false);
         }
     }
 
@@ -433,6 +453,16 @@ impl XwmHandler for Data {
                 let (window, _) = mapped.windows().find(|(w, _)| w == &surface).unwrap();
                 workspace.fullscreen_request(&window, &output)
             }
+        } else if let Some((window, _)) = self
+            .state
+            .common
+            .shell
+            .pending_windows
+            .iter()
+            .find(|(w, _)| w == &surface)
+        {
+            // applied once the window gets mapped
+            window.set_fullscreen(true);
         }
     }
 
@@ -449,6 +479,16 @@ impl XwmHandler for Data {
                 let (window, _) = mapped.windows().find(|(w, _)| w == &surface).unwrap();
                 workspace.unfullscreen_request(&window)
             }
+        } else if let Some((window, _)) = self
+            .state
+            .common
+            .shell
+            .pending_windows
+            .iter()
+            .find(|(w, _)| w == &surface)
+        {
+            // applied once the window gets mapped
+            window.set_fullscreen(false);
         }
     }
 